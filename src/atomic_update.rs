@@ -0,0 +1,157 @@
+//! Atomic updates, partial changes of documents keyed by their id.
+//!
+//! ```rust
+//! use rsolr::Client;
+//! use rsolr::atomic_update::AtomicUpdate;
+//!
+//! fn update() {
+//!     let updates = vec![
+//!         AtomicUpdate::new("book1").set("price", 12.5).add_distinct("tags", "sale").inc("views", 1),
+//!         AtomicUpdate::new("book2").remove("tags", "sale").version(1632740120218042368)
+//!     ];
+//!     Client::new("http://solr:8983", "collection")
+//!         .atomic_update(updates)
+//!         .auto_commit()
+//!         .run().expect("request failed.");
+//! }
+//! ```
+//!
+//! With a version, Solr applies the update only if the stored `_version_` matches, see
+//! [optimistic concurrency](https://solr.apache.org/guide/solr/latest/indexing-guide/partial-document-updates.html#optimistic-concurrency).
+//!
+//! ```rust
+//! use rsolr::atomic_update::AtomicUpdate;
+//! use serde_json::json;
+//!
+//! let update = AtomicUpdate::with_key("sku", 42).set("title", "Rust").remove_regex("tags", "^tmp-.*").version(-1);
+//! assert_eq!(serde_json::to_value(&update).unwrap(), json!({
+//!     "sku": 42,
+//!     "title": {"set": "Rust"},
+//!     "tags": {"removeregex": "^tmp-.*"},
+//!     "_version_": -1
+//! }));
+//! ```
+
+use serde::{Serialize, Serializer};
+use serde::ser::SerializeMap;
+use serde_json::{Map, Value};
+
+/// Changes of one document, the operations of a field are applied together.
+/// Operations on the key field or `_version_` are ignored, these come from the id and [AtomicUpdate::version].
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtomicUpdate {
+    key: String,
+    id: Value,
+    fields: Map<String, Value>,
+    version: Option<i64>
+}
+
+impl AtomicUpdate {
+
+    /// Update of the document with the id in the `id` field.
+    pub fn new<I: Serialize>(id: I) -> Self {
+        Self::with_key("id", id)
+    }
+
+    /// Update of the document with the id in a uniqueKey other than `id`.
+    pub fn with_key<I: Serialize>(key: &str, id: I) -> Self {
+        AtomicUpdate { key: key.to_owned(), id: serde_json::to_value(id).unwrap(), fields: Map::new(), version: None }
+    }
+
+    /// Replaces the field value, `Value::Null` removes it.
+    pub fn set<V: Serialize>(self, field: &str, value: V) -> Self {
+        self.operation(field, "set", value)
+    }
+
+    /// Adds the value or values to a multivalued field.
+    pub fn add<V: Serialize>(self, field: &str, value: V) -> Self {
+        self.operation(field, "add", value)
+    }
+
+    /// Adds the value or values to a multivalued field if not present yet.
+    pub fn add_distinct<V: Serialize>(self, field: &str, value: V) -> Self {
+        self.operation(field, "add-distinct", value)
+    }
+
+    /// Removes all occurrences of the value or values from a multivalued field.
+    pub fn remove<V: Serialize>(self, field: &str, value: V) -> Self {
+        self.operation(field, "remove", value)
+    }
+
+    /// Removes the values matching the regular expression from a multivalued field.
+    pub fn remove_regex(self, field: &str, pattern: &str) -> Self {
+        self.operation(field, "removeregex", pattern)
+    }
+
+    /// Increments a numeric field, negative amounts decrement.
+    pub fn inc<V: Serialize>(self, field: &str, amount: V) -> Self {
+        self.operation(field, "inc", amount)
+    }
+
+    /// Applies only if `_version_` matches: positive is the exact version, 1 an existing document, negative a missing one.
+    pub fn version(mut self, version: i64) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    fn operation<V: Serialize>(mut self, field: &str, operation: &str, value: V) -> Self {
+        if field == self.key || field == "_version_" {
+            return self
+        }
+        let value = serde_json::to_value(value).unwrap();
+        let operations = self.fields.entry(field).or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(operations) = operations {
+            operations.insert(operation.to_owned(), value);
+        }
+        self
+    }
+}
+
+impl Serialize for AtomicUpdate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry(&self.key, &self.id)?;
+        for (field, operations) in &self.fields {
+            map.serialize_entry(field, operations)?;
+        }
+        if let Some(version) = self.version {
+            map.serialize_entry("_version_", &version)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    #[test]
+    fn operations_of_a_field_are_merged() {
+        let update = AtomicUpdate::new("doc1")
+            .add("tags", ["a", "b"])
+            .remove("tags", "c")
+            .add_distinct("categories", "x")
+            .inc("views", -2)
+            .set("note", Value::Null);
+        assert_eq!(serde_json::to_value(&update).unwrap(), json!({
+            "id": "doc1",
+            "tags": {"add": ["a", "b"], "remove": "c"},
+            "categories": {"add-distinct": "x"},
+            "views": {"inc": -2},
+            "note": {"set": null}
+        }));
+    }
+
+    #[test]
+    fn later_operation_of_the_same_kind_wins() {
+        let update = AtomicUpdate::new(7).set("price", 1).set("price", 2).version(1);
+        assert_eq!(serde_json::to_string(&update).unwrap(), r#"{"id":7,"price":{"set":2},"_version_":1}"#);
+    }
+
+    #[test]
+    fn operations_on_the_key_or_version_are_ignored() {
+        let update = AtomicUpdate::with_key("sku", 42).set("sku", 43).inc("_version_", 1).remove_regex("tags", "^tmp-");
+        assert_eq!(serde_json::to_string(&update).unwrap(), r#"{"sku":42,"tags":{"removeregex":"^tmp-"}}"#);
+    }
+}
@@ -0,0 +1,423 @@
+//! Typed configuration of the DisMax and eDisMax query parsers.
+//!
+//! The params can be built in code or deserialized, i.e. from a relevance config file.
+//!
+//! ```rust
+//! use rsolr::Client;
+//! use rsolr::dismax::{EDisMaxParams, FieldBoost, MinimumShouldMatch};
+//!
+//! fn search() {
+//!     let params: EDisMaxParams = serde_json::from_str(r#"{
+//!         "qf": ["title^2", "body"],
+//!         "mm": "2<-25%",
+//!         "tie": 0.1,
+//!         "lowercaseOperators": false
+//!     }"#).expect("invalid config");
+//!
+//!     let params = params
+//!         .pf(vec![FieldBoost::boosted("title", 5.0)])
+//!         .boost("recip(ms(NOW,created),3.16e-11,1,1)");
+//!
+//!     Client::new("http://solr:8983", "collection")
+//!         .select("ipod charger")
+//!         .edismax_params(&params)
+//!         .run().expect("request failed.");
+//! }
+//! ```
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::error::RSolrError;
+pub use crate::query::MinimumShouldMatch;
+
+/// A field with an optional boost, i.e. `title^2`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct FieldBoost {
+    pub field: String,
+    pub boost: Option<f32>
+}
+
+impl FieldBoost {
+
+    pub fn new(field: &str) -> Self {
+        FieldBoost { field: field.to_owned(), boost: None }
+    }
+
+    pub fn boosted(field: &str, boost: f32) -> Self {
+        FieldBoost { field: field.to_owned(), boost: Some(boost) }
+    }
+}
+
+impl Display for FieldBoost {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.boost {
+            Some(boost) => write!(f, "{}^{}", self.field, boost),
+            None => write!(f, "{}", self.field)
+        }
+    }
+}
+
+impl FromStr for FieldBoost {
+    type Err = RSolrError;
+
+    fn from_str(field_boost: &str) -> Result<Self, Self::Err> {
+        let (field, boost) = match field_boost.split_once('^') {
+            Some((field, boost)) => match boost.parse::<f32>() {
+                Ok(boost) if boost >= 0.0 => (field, Some(boost)),
+                _ => return Err(RSolrError::Validation(format!("invalid boost in `{}`", field_boost)))
+            },
+            None => (field_boost, None)
+        };
+        if field.is_empty() || field.contains(char::is_whitespace) {
+            return Err(RSolrError::Validation(format!("invalid field in `{}`", field_boost)));
+        }
+        Ok(FieldBoost { field: field.to_owned(), boost })
+    }
+}
+
+impl TryFrom<String> for FieldBoost {
+    type Error = RSolrError;
+
+    fn try_from(field_boost: String) -> Result<Self, Self::Error> {
+        field_boost.parse()
+    }
+}
+
+impl From<FieldBoost> for String {
+    fn from(field_boost: FieldBoost) -> Self {
+        field_boost.to_string()
+    }
+}
+
+/// Parameters of the DisMax query parser.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisMaxParams {
+    qf: Vec<FieldBoost>,
+    mm: Option<MinimumShouldMatch>,
+    pf: Vec<FieldBoost>,
+    ps: Option<u32>,
+    qs: Option<u32>,
+    tie: Option<f32>,
+    bq: Vec<String>,
+    bf: Vec<String>
+}
+
+impl DisMaxParams {
+
+    pub fn new() -> Self {
+        DisMaxParams::default()
+    }
+
+    /// Query fields with their boosts.
+    pub fn qf(mut self, fields: Vec<FieldBoost>) -> Self {
+        self.qf = fields;
+        self
+    }
+
+    /// Minimum should match.
+    pub fn mm(mut self, mm: MinimumShouldMatch) -> Self {
+        self.mm = Some(mm);
+        self
+    }
+
+    /// Phrase fields with their boosts.
+    pub fn pf(mut self, fields: Vec<FieldBoost>) -> Self {
+        self.pf = fields;
+        self
+    }
+
+    /// Phrase slop of the phrase fields.
+    pub fn ps(mut self, slop: u32) -> Self {
+        self.ps = Some(slop);
+        self
+    }
+
+    /// Query slop of explicit phrases in the query.
+    pub fn qs(mut self, slop: u32) -> Self {
+        self.qs = Some(slop);
+        self
+    }
+
+    /// Tie breaker between the field scores.
+    pub fn tie(mut self, tie: f32) -> Self {
+        self.tie = Some(tie);
+        self
+    }
+
+    /// Adds a boost query.
+    pub fn bq(mut self, query: &str) -> Self {
+        self.bq.push(query.to_owned());
+        self
+    }
+
+    /// Adds an additive boost function, a function::Function or its string.
+    pub fn bf<F: Display>(mut self, function: F) -> Self {
+        self.bf.push(function.to_string());
+        self
+    }
+
+    /// Request parameters in order, multivalued ones repeated.
+    pub fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![];
+        push_fields(&mut params, "qf", &self.qf);
+        push_option(&mut params, "mm", &self.mm);
+        push_fields(&mut params, "pf", &self.pf);
+        push_option(&mut params, "ps", &self.ps);
+        push_option(&mut params, "qs", &self.qs);
+        push_option(&mut params, "tie", &self.tie);
+        push_all(&mut params, "bq", &self.bq);
+        push_all(&mut params, "bf", &self.bf);
+        params
+    }
+}
+
+/// Parameters of the eDisMax query parser.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EDisMaxParams {
+    qf: Vec<FieldBoost>,
+    mm: Option<MinimumShouldMatch>,
+    pf: Vec<FieldBoost>,
+    pf2: Vec<FieldBoost>,
+    pf3: Vec<FieldBoost>,
+    ps: Option<u32>,
+    ps2: Option<u32>,
+    ps3: Option<u32>,
+    qs: Option<u32>,
+    tie: Option<f32>,
+    bq: Vec<String>,
+    bf: Vec<String>,
+    boost: Vec<String>,
+    uf: Option<String>,
+    #[serde(rename = "lowercaseOperators")]
+    lowercase_operators: Option<bool>,
+    sow: Option<bool>,
+    stopwords: Option<bool>
+}
+
+impl EDisMaxParams {
+
+    pub fn new() -> Self {
+        EDisMaxParams::default()
+    }
+
+    /// Query fields with their boosts.
+    pub fn qf(mut self, fields: Vec<FieldBoost>) -> Self {
+        self.qf = fields;
+        self
+    }
+
+    /// Minimum should match.
+    pub fn mm(mut self, mm: MinimumShouldMatch) -> Self {
+        self.mm = Some(mm);
+        self
+    }
+
+    /// Phrase fields with their boosts.
+    pub fn pf(mut self, fields: Vec<FieldBoost>) -> Self {
+        self.pf = fields;
+        self
+    }
+
+    /// Bigram phrase fields with their boosts.
+    pub fn pf2(mut self, fields: Vec<FieldBoost>) -> Self {
+        self.pf2 = fields;
+        self
+    }
+
+    /// Trigram phrase fields with their boosts.
+    pub fn pf3(mut self, fields: Vec<FieldBoost>) -> Self {
+        self.pf3 = fields;
+        self
+    }
+
+    /// Phrase slop of the phrase fields.
+    pub fn ps(mut self, slop: u32) -> Self {
+        self.ps = Some(slop);
+        self
+    }
+
+    /// Phrase slop of the bigram phrase fields.
+    pub fn ps2(mut self, slop: u32) -> Self {
+        self.ps2 = Some(slop);
+        self
+    }
+
+    /// Phrase slop of the trigram phrase fields.
+    pub fn ps3(mut self, slop: u32) -> Self {
+        self.ps3 = Some(slop);
+        self
+    }
+
+    /// Query slop of explicit phrases in the query.
+    pub fn qs(mut self, slop: u32) -> Self {
+        self.qs = Some(slop);
+        self
+    }
+
+    /// Tie breaker between the field scores.
+    pub fn tie(mut self, tie: f32) -> Self {
+        self.tie = Some(tie);
+        self
+    }
+
+    /// Adds a boost query.
+    pub fn bq(mut self, query: &str) -> Self {
+        self.bq.push(query.to_owned());
+        self
+    }
+
+    /// Adds an additive boost function, a function::Function or its string.
+    pub fn bf<F: Display>(mut self, function: F) -> Self {
+        self.bf.push(function.to_string());
+        self
+    }
+
+    /// Adds a multiplicative boost function, a function::Function or its string.
+    pub fn boost<F: Display>(mut self, function: F) -> Self {
+        self.boost.push(function.to_string());
+        self
+    }
+
+    /// User fields, the fields users may query explicitly, i.e. `title body -secret`.
+    pub fn uf(mut self, user_fields: &str) -> Self {
+        self.uf = Some(user_fields.to_owned());
+        self
+    }
+
+    /// Whether lowercase `and` and `or` are treated as operators.
+    pub fn lowercase_operators(mut self, lowercase_operators: bool) -> Self {
+        self.lowercase_operators = Some(lowercase_operators);
+        self
+    }
+
+    /// Split on whitespace before analysis.
+    pub fn sow(mut self, split_on_whitespace: bool) -> Self {
+        self.sow = Some(split_on_whitespace);
+        self
+    }
+
+    /// Whether the StopFilterFactory of the query analyzer is applied.
+    pub fn stopwords(mut self, stopwords: bool) -> Self {
+        self.stopwords = Some(stopwords);
+        self
+    }
+
+    /// Request parameters in order, multivalued ones repeated.
+    pub fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![];
+        push_fields(&mut params, "qf", &self.qf);
+        push_option(&mut params, "mm", &self.mm);
+        push_fields(&mut params, "pf", &self.pf);
+        push_fields(&mut params, "pf2", &self.pf2);
+        push_fields(&mut params, "pf3", &self.pf3);
+        push_option(&mut params, "ps", &self.ps);
+        push_option(&mut params, "ps2", &self.ps2);
+        push_option(&mut params, "ps3", &self.ps3);
+        push_option(&mut params, "qs", &self.qs);
+        push_option(&mut params, "tie", &self.tie);
+        push_all(&mut params, "bq", &self.bq);
+        push_all(&mut params, "bf", &self.bf);
+        push_all(&mut params, "boost", &self.boost);
+        push_option(&mut params, "uf", &self.uf);
+        push_option(&mut params, "lowercaseOperators", &self.lowercase_operators);
+        push_option(&mut params, "sow", &self.sow);
+        push_option(&mut params, "stopwords", &self.stopwords);
+        params
+    }
+}
+
+fn push_fields(params: &mut Vec<(&'static str, String)>, key: &'static str, fields: &[FieldBoost]) {
+    if !fields.is_empty() {
+        let fields: Vec<String> = fields.iter().map(|field| field.to_string()).collect();
+        params.push((key, fields.join(" ")));
+    }
+}
+
+fn push_option<V: Display>(params: &mut Vec<(&'static str, String)>, key: &'static str, value: &Option<V>) {
+    if let Some(value) = value {
+        params.push((key, value.to_string()));
+    }
+}
+
+fn push_all(params: &mut Vec<(&'static str, String)>, key: &'static str, values: &[String]) {
+    for value in values {
+        params.push((key, value.to_owned()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_boost_parsed_and_rendered() {
+        assert_eq!("title^2.5".parse::<FieldBoost>().unwrap(), FieldBoost::boosted("title", 2.5));
+        assert_eq!("title".parse::<FieldBoost>().unwrap(), FieldBoost::new("title"));
+        assert_eq!(FieldBoost::boosted("title", 2.0).to_string(), "title^2");
+        assert!("title^x".parse::<FieldBoost>().is_err());
+        assert!("^2".parse::<FieldBoost>().is_err());
+    }
+
+    #[test]
+    fn dismax_params_rendered_in_order() {
+        let params = DisMaxParams::new()
+            .qf(vec![FieldBoost::boosted("title", 2.0), FieldBoost::new("body")])
+            .mm(MinimumShouldMatch::count(2))
+            .tie(0.1)
+            .bq("category:books^2")
+            .bq("inStock:true")
+            .bf("log(popularity)");
+        assert_eq!(params.params(), vec![
+            ("qf", "title^2 body".to_owned()),
+            ("mm", "2".to_owned()),
+            ("tie", "0.1".to_owned()),
+            ("bq", "category:books^2".to_owned()),
+            ("bq", "inStock:true".to_owned()),
+            ("bf", "log(popularity)".to_owned())
+        ]);
+    }
+
+    #[test]
+    fn edismax_params_deserialized_from_config() {
+        let params: EDisMaxParams = serde_json::from_str(r#"{
+            "qf": ["title^2", "body"],
+            "pf2": ["title"],
+            "ps2": 1,
+            "mm": "2<-25%",
+            "boost": ["recip(ms(NOW,created),3.16e-11,1,1)"],
+            "uf": "title body",
+            "lowercaseOperators": false,
+            "sow": true,
+            "stopwords": false
+        }"#).unwrap();
+        assert_eq!(params.params(), vec![
+            ("qf", "title^2 body".to_owned()),
+            ("mm", "2<-25%".to_owned()),
+            ("pf2", "title".to_owned()),
+            ("ps2", "1".to_owned()),
+            ("boost", "recip(ms(NOW,created),3.16e-11,1,1)".to_owned()),
+            ("uf", "title body".to_owned()),
+            ("lowercaseOperators", "false".to_owned()),
+            ("sow", "true".to_owned()),
+            ("stopwords", "false".to_owned())
+        ]);
+    }
+
+    #[test]
+    fn config_with_invalid_mm_or_unknown_field_rejected() {
+        assert!(serde_json::from_str::<EDisMaxParams>(r#"{"mm": "2<<3"}"#).is_err());
+        assert!(serde_json::from_str::<DisMaxParams>(r#"{"pf2": ["title"]}"#).is_err());
+    }
+
+    #[test]
+    fn params_serialized_back_to_config() {
+        let params = DisMaxParams::new().qf(vec![FieldBoost::boosted("title", 2.0)]).mm(MinimumShouldMatch::percent(50));
+        let config = serde_json::to_value(&params).unwrap();
+        assert_eq!(config["qf"], serde_json::json!(["title^2"]));
+        assert_eq!(serde_json::from_value::<DisMaxParams>(config).unwrap(), params);
+    }
+}
@@ -1,5 +1,6 @@
 use std::error::Error;
 use thiserror::Error;
+use crate::solr_response::UpdateResponse;
 
 #[derive(Error, Debug)]
 pub enum RSolrError {
@@ -10,6 +11,8 @@ pub enum RSolrError {
     },
     #[error("Solr cannot find the requested resource.")]
     NotFound,
+    #[error("Version conflict: `{0}`")]
+    VersionConflict(String),
     #[error("Syntax error in Solr request: `{0}`")]
     Syntax(String),
     #[error("Generic Solr error.")]
@@ -20,5 +23,19 @@ pub enum RSolrError {
         body_text: String
     },
     #[error("JSON deserialization failed: `{0}`")]
-    Serialization(String)
+    Serialization(String),
+    #[error("Invalid request parameter: `{0}`")]
+    Validation(String),
+    #[error("Invalid query at position {position}: {message}")]
+    QueryParse {
+        message: String,
+        position: usize
+    },
+    #[error("Deleting stopped after {} deleted chunks: {source}", .deleted.len())]
+    PartialDelete {
+        /// Update responses of the chunks deleted before the failure.
+        deleted: Vec<UpdateResponse>,
+        #[source]
+        source: Box<RSolrError>
+    }
 }
\ No newline at end of file
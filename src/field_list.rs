@@ -0,0 +1,258 @@
+//! Field list (`fl`) builder with aliases, function pseudo-fields and document transformers.
+//!
+//! ```rust
+//! use serde::Deserialize;
+//! use rsolr::Client;
+//! use rsolr::field_list::{FieldList, Transformer};
+//! use rsolr::solr_response::Scored;
+//!
+//! #[derive(Deserialize, Clone, Default)]
+//! struct Book {
+//!     id: String,
+//!     title: Vec<String>
+//! }
+//!
+//! fn scored_books() -> Vec<Scored<Book>> {
+//!     let mut client = Client::new("http://solr:8983", "collection");
+//!     let fields = FieldList::new()
+//!         .field("id")
+//!         .alias("title", "title_t")
+//!         .score()
+//!         .transformer(Transformer::explain().param("style", "nl"));
+//!     client
+//!         .select("*:*")
+//!         .field_list(&fields)
+//!         .run().expect("request failed.");
+//!     client.get_response::<Scored<Book>>().expect("Serialization failed").response.expect("No response").docs
+//! }
+//! ```
+
+use std::fmt::{Display, Formatter};
+use crate::query::quote_param_value;
+
+/// A document transformer, rendered as `[name key=value ...]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transformer {
+    name: String,
+    params: Vec<(String, String)>
+}
+
+impl Transformer {
+
+    /// Any transformer by its name, i.e. a custom one.
+    pub fn new(name: &str) -> Self {
+        Transformer { name: name.to_owned(), params: vec![] }
+    }
+
+    /// `[child]`, returns nested child documents.
+    pub fn child() -> Self {
+        Transformer::new("child")
+    }
+
+    /// `[explain]`, returns the score explanation.
+    pub fn explain() -> Self {
+        Transformer::new("explain")
+    }
+
+    /// `[shard]`, returns the shard the document came from.
+    pub fn shard() -> Self {
+        Transformer::new("shard")
+    }
+
+    /// `[docid]`, returns the internal Lucene document id.
+    pub fn docid() -> Self {
+        Transformer::new("docid")
+    }
+
+    /// `[value]`, returns a constant value, use value_type to set its type.
+    pub fn value(value: &str) -> Self {
+        Transformer::new("value").param("v", value)
+    }
+
+    /// `[subquery]`, use it through FieldList::subquery.
+    pub fn subquery() -> Self {
+        Transformer::new("subquery")
+    }
+
+    /// `[features]`, returns the LTR feature values.
+    pub fn features() -> Self {
+        Transformer::new("features")
+    }
+
+    /// Adds a parameter to the transformer.
+    pub fn param(mut self, key: &str, value: &str) -> Self {
+        self.params.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Shorthand for `parentFilter` of `[child]`.
+    pub fn parent_filter(self, filter: &str) -> Self {
+        self.param("parentFilter", filter)
+    }
+
+    /// Shorthand for `childFilter` of `[child]`.
+    pub fn child_filter(self, filter: &str) -> Self {
+        self.param("childFilter", filter)
+    }
+
+    /// Shorthand for `limit` of `[child]`.
+    pub fn limit(self, limit: u32) -> Self {
+        self.param("limit", &limit.to_string())
+    }
+
+    /// Shorthand for `fl` of `[child]`.
+    pub fn fl(self, fl: &str) -> Self {
+        self.param("fl", fl)
+    }
+
+    /// Shorthand for `t` of `[value]`, i.e. int, float, double, date.
+    pub fn value_type(self, value_type: &str) -> Self {
+        self.param("t", value_type)
+    }
+
+    /// Shorthand for `store` of `[features]`.
+    pub fn store(self, store: &str) -> Self {
+        self.param("store", store)
+    }
+
+    /// Shorthand for `efi.*` external feature information of `[features]`.
+    pub fn efi(self, key: &str, value: &str) -> Self {
+        self.param(&format!("efi.{}", key), value)
+    }
+}
+
+impl Display for Transformer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}", self.name)?;
+        for (key, value) in &self.params {
+            write!(f, " {}={}", key, quote_param_value(value))?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// Builder of the 'fl' parameter.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldList {
+    entries: Vec<String>,
+    params: Vec<(String, String)>
+}
+
+impl FieldList {
+
+    pub fn new() -> Self {
+        FieldList::default()
+    }
+
+    /// Adds a stored field.
+    pub fn field(mut self, field: &str) -> Self {
+        self.entries.push(field.to_owned());
+        self
+    }
+
+    /// Adds a field name glob, i.e. `*_s`.
+    pub fn glob(self, pattern: &str) -> Self {
+        self.field(pattern)
+    }
+
+    /// Adds a field renamed in the response, rendered as `alias:field`.
+    pub fn alias(self, alias: &str, field: &str) -> Self {
+        self.field(&format!("{}:{}", alias, field))
+    }
+
+    /// Adds a function query as pseudo-field, its key in the response is the function itself.
+    pub fn function<F: Display>(self, function: F) -> Self {
+        self.field(&function.to_string())
+    }
+
+    /// Adds a function query as pseudo-field with an alias.
+    pub fn alias_function<F: Display>(self, alias: &str, function: F) -> Self {
+        self.alias(alias, &function.to_string())
+    }
+
+    /// Adds the `score` pseudo-field.
+    pub fn score(self) -> Self {
+        self.field("score")
+    }
+
+    /// Adds a document transformer.
+    pub fn transformer(self, transformer: Transformer) -> Self {
+        self.field(&transformer.to_string())
+    }
+
+    /// Adds a document transformer with an alias, i.e. required for `[value]`.
+    pub fn alias_transformer(self, alias: &str, transformer: Transformer) -> Self {
+        self.alias(alias, &transformer.to_string())
+    }
+
+    /// Adds a `[subquery]` under the alias with its query as `alias.q`.
+    pub fn subquery(self, alias: &str, query: &str) -> Self {
+        self.alias_transformer(alias, Transformer::subquery())
+            .subquery_param(alias, "q", query)
+    }
+
+    /// Adds a parameter of the subquery, i.e. `alias.fl` or `alias.rows`.
+    pub fn subquery_param(mut self, alias: &str, key: &str, value: &str) -> Self {
+        self.params.push((format!("{}.{}", alias, key), value.to_owned()));
+        self
+    }
+
+    /// Request parameters required besides 'fl', i.e. subquery parameters.
+    pub fn params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
+}
+
+impl Display for FieldList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.entries.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_fields_globs_and_score() {
+        let fields = FieldList::new().field("id").glob("*_s").score();
+        assert_eq!(fields.to_string(), "id,*_s,score");
+    }
+
+    #[test]
+    fn renders_aliases_and_functions() {
+        let fields = FieldList::new()
+            .alias("title", "title_t")
+            .function("sum(a,b)")
+            .alias_function("total", "sum(a,b)");
+        assert_eq!(fields.to_string(), "title:title_t,sum(a,b),total:sum(a,b)");
+    }
+
+    #[test]
+    fn renders_transformers_with_quoted_params() {
+        let fields = FieldList::new()
+            .transformer(Transformer::child().parent_filter("type:parent").child_filter("color:dark blue").limit(5))
+            .transformer(Transformer::explain().param("style", "nl"))
+            .transformer(Transformer::shard())
+            .transformer(Transformer::docid())
+            .alias_transformer("origin", Transformer::value("web").value_type("string"))
+            .transformer(Transformer::features().store("feats").efi("user_query", "it's"));
+        assert_eq!(
+            fields.to_string(),
+            "[child parentFilter=type:parent childFilter='color:dark blue' limit=5],[explain style=nl],[shard],[docid],origin:[value v=web t=string],[features store=feats efi.user_query='it\\'s']"
+        );
+    }
+
+    #[test]
+    fn subquery_adds_alias_and_params() {
+        let fields = FieldList::new()
+            .field("id")
+            .subquery("depts", "{!terms f=id v=$row.dept_id}")
+            .subquery_param("depts", "fl", "name");
+        assert_eq!(fields.to_string(), "id,depts:[subquery]");
+        assert_eq!(fields.params(), &vec![
+            ("depts.q".to_owned(), "{!terms f=id v=$row.dept_id}".to_owned()),
+            ("depts.fl".to_owned(), "name".to_owned())
+        ]);
+    }
+}
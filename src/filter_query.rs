@@ -0,0 +1,256 @@
+//! Filter query (`fq`) with caching controls and tags.
+//!
+//! ```rust
+//! use rsolr::Client;
+//! use rsolr::filter_query::FilterQuery;
+//!
+//! fn filtered() {
+//!     Client::new("http://solr:8983", "collection")
+//!         .select("*:*")
+//!         .filter_query("inStock:true")
+//!         .filter_query(FilterQuery::new("price:[* TO 100]").cache(false).cost(50))
+//!         .filter_query(FilterQuery::new("{!frange l=0 u=10}log(popularity)").post_filter())
+//!         .filter_query(FilterQuery::new("type:book").tag("type"))
+//!         .run().expect("request failed.");
+//! }
+//! ```
+
+use std::fmt::{Display, Formatter};
+use crate::query::{Bound, LocalParams, Query, Range, Stringable};
+
+/// The cost from which Solr runs a non-cached filter as a post filter.
+pub const POST_FILTER_COST: u32 = 100;
+
+/// A filter query with optional `cache`, `cost` and `tag` local params.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilterQuery {
+    query: String,
+    cache: Option<bool>,
+    cost: Option<u32>,
+    tags: Vec<String>
+}
+
+impl FilterQuery {
+
+    pub fn new(query: &str) -> Self {
+        FilterQuery { query: query.to_owned(), cache: None, cost: None, tags: vec![] }
+    }
+
+    /// Function range filter, `{!frange}`, matching documents whose function value is in the range.
+    pub fn frange<F: Display, R: Into<Range>>(function: F, range: R) -> Self {
+        let range = range.into();
+        let mut local_params = LocalParams::new("frange");
+        match range.lower {
+            Bound::Inclusive(lower) => local_params = local_params.param("l", &lower),
+            Bound::Exclusive(lower) => local_params = local_params.param("l", &lower).param("incl", "false"),
+            Bound::Unbounded => ()
+        }
+        match range.upper {
+            Bound::Inclusive(upper) => local_params = local_params.param("u", &upper),
+            Bound::Exclusive(upper) => local_params = local_params.param("u", &upper).param("incu", "false"),
+            Bound::Unbounded => ()
+        }
+        FilterQuery::new(&local_params.wrap(&function.to_string()))
+    }
+
+    /// Sets whether Solr should cache the filter.
+    pub fn cache(mut self, cache: bool) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Sets the evaluation order of non-cached filters, lower runs first.
+    pub fn cost(mut self, cost: u32) -> Self {
+        self.cost = Some(cost);
+        self
+    }
+
+    /// Tags the filter, so facets can exclude it with `{!ex=tag}`.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_owned());
+        self
+    }
+
+    /// Turns the filter into a post filter: not cached, with cost at least 100.
+    /// Only query parsers supporting post filtering (i.e. frange, collapse) honour it.
+    pub fn post_filter(mut self) -> Self {
+        self.cache = Some(false);
+        self.cost = Some(self.cost.unwrap_or(0).max(POST_FILTER_COST));
+        self
+    }
+
+    /// Keys of the params set, these override the same keys of a `{!...}` block in the query.
+    fn keys(&self) -> Vec<&str> {
+        [("cache", self.cache.is_some()), ("cost", self.cost.is_some()), ("tag", !self.tags.is_empty())].into_iter()
+            .filter(|(_, set)| *set)
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    fn local_params(&self) -> LocalParams {
+        let mut local_params = LocalParams::empty();
+        if let Some(cache) = self.cache {
+            local_params = local_params.param("cache", &cache.to_string());
+        }
+        if let Some(cost) = self.cost {
+            local_params = local_params.param("cost", &cost.to_string());
+        }
+        if !self.tags.is_empty() {
+            local_params = local_params.param("tag", &self.tags.join(","));
+        }
+        local_params
+    }
+}
+
+impl Display for FilterQuery {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let local_params = self.local_params();
+        if local_params.is_empty() {
+            return write!(f, "{}", self.query);
+        }
+
+        match leading_local_params(&self.query) {
+            Some((params, end)) => {
+                let keys = self.keys();
+                let inner = local_params.inner();
+                let mut params: Vec<&str> = params.into_iter().filter(|param| !keys.contains(&param_key(param))).collect();
+                params.push(&inner);
+                write!(f, "{{!{}}}{}", params.join(" "), &self.query[end + 1..])
+            },
+            None => write!(f, "{}", local_params.wrap(&self.query))
+        }
+    }
+}
+
+impl From<&str> for FilterQuery {
+    fn from(query: &str) -> Self {
+        FilterQuery::new(query)
+    }
+}
+
+impl From<String> for FilterQuery {
+    fn from(query: String) -> Self {
+        FilterQuery::new(&query)
+    }
+}
+
+impl From<&Query> for FilterQuery {
+    fn from(query: &Query) -> Self {
+        FilterQuery::new(&query.as_str())
+    }
+}
+
+impl From<Query> for FilterQuery {
+    fn from(query: Query) -> Self {
+        FilterQuery::from(&query)
+    }
+}
+
+/// Params of a leading `{!...}` block split at whitespace outside quoted values, and the position of
+/// its closing brace.
+fn leading_local_params(query: &str) -> Option<(Vec<&str>, usize)> {
+    if !query.starts_with("{!") {
+        return None;
+    }
+    let mut params = vec![];
+    let mut start = 2;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (position, char) in query.char_indices().skip(2) {
+        match (quote, char) {
+            _ if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if q == c => quote = None,
+            (Some(_), _) => (),
+            (None, '\'') | (None, '"') => quote = Some(char),
+            (None, '}') => {
+                params.push(&query[start..position]);
+                return Some((params.into_iter().filter(|param| !param.is_empty()).collect(), position));
+            },
+            (None, c) if c.is_whitespace() => {
+                params.push(&query[start..position]);
+                start = position + c.len_utf8();
+            },
+            _ => ()
+        }
+    }
+    None
+}
+
+fn param_key(param: &str) -> &str {
+    param.split('=').next().unwrap_or(param)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::function::Function;
+    use crate::query::Term;
+    use super::*;
+
+    #[test]
+    fn renders_plain_filter_without_local_params() {
+        assert_eq!(FilterQuery::new("inStock:true").to_string(), "inStock:true");
+    }
+
+    #[test]
+    fn renders_cache_and_cost() {
+        let filter = FilterQuery::new("inStock:true").cache(false).cost(5);
+        assert_eq!(filter.to_string(), "{!cache=false cost=5}inStock:true");
+    }
+
+    #[test]
+    fn renders_tags_joined() {
+        let filter = FilterQuery::new("type:book").tag("type").tag("all");
+        assert_eq!(filter.to_string(), "{!tag=type,all}type:book");
+    }
+
+    #[test]
+    fn quotes_tag_with_special_characters() {
+        let filter = FilterQuery::new("type:book").tag("my tag");
+        assert_eq!(filter.to_string(), "{!tag='my tag'}type:book");
+    }
+
+    #[test]
+    fn post_filter_disables_cache_and_raises_cost() {
+        let filter = FilterQuery::new("x:y").cost(20).post_filter();
+        assert_eq!(filter.to_string(), "{!cache=false cost=100}x:y");
+
+        let filter = FilterQuery::new("x:y").cost(200).post_filter();
+        assert_eq!(filter.to_string(), "{!cache=false cost=200}x:y");
+    }
+
+    #[test]
+    fn merges_into_existing_local_params() {
+        let filter = FilterQuery::new("{!frange l=0 u='1}0'}log(popularity)").post_filter();
+        assert_eq!(filter.to_string(), "{!frange l=0 u='1}0' cache=false cost=100}log(popularity)");
+    }
+
+    #[test]
+    fn overrides_the_same_keys_of_existing_local_params() {
+        let filter = FilterQuery::new("{!cache=true tag=x}a:b").cache(false).tag("y");
+        assert_eq!(filter.to_string(), "{!cache=false tag=y}a:b");
+
+        let filter = FilterQuery::new("{!frange cache=true cost=5 tag='a b' l=0}log(popularity)").post_filter();
+        assert_eq!(filter.to_string(), "{!frange tag='a b' l=0 cache=false cost=100}log(popularity)");
+    }
+
+    #[test]
+    fn merges_into_local_params_built_by_query() {
+        let terms = LocalParams::new("terms").param("f", "id").wrap("1,2,3");
+        assert_eq!(FilterQuery::new(&terms).tag("ids").to_string(), "{!terms f=id tag=ids}1,2,3");
+    }
+
+    #[test]
+    fn frange_renders_bounds_and_inclusiveness() {
+        let popularity = Function::log(Function::field("popularity"));
+        assert_eq!(FilterQuery::frange(&popularity, 0..=10).to_string(), "{!frange l=0 u=10}log(popularity)");
+        assert_eq!(FilterQuery::frange(&popularity, 0..10).post_filter().to_string(), "{!frange l=0 u=10 incu=false cache=false cost=100}log(popularity)");
+        assert_eq!(FilterQuery::frange("sum(a,b)", Range::greater_than(0.5)).to_string(), "{!frange l=0.5 incl=false}sum(a,b)");
+    }
+
+    #[test]
+    fn created_from_query() {
+        let query = Query::from_term(Term::from_str("book").in_field("type"));
+        assert_eq!(FilterQuery::from(query).tag("t").to_string(), "{!tag=t}type:book");
+    }
+}
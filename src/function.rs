@@ -0,0 +1,233 @@
+//! Function query expressions, rendered to Solr syntax.
+//!
+//! Functions are usable wherever a function string is: sort, field list, boost functions and
+//! frange filters.
+//!
+//! ```rust
+//! use rsolr::Client;
+//! use rsolr::dismax::EDisMaxParams;
+//! use rsolr::field_list::FieldList;
+//! use rsolr::filter_query::FilterQuery;
+//! use rsolr::function::Function;
+//! use rsolr::query::Date;
+//! use rsolr::sort::{Direction, Sort};
+//!
+//! fn boosted() {
+//!     let recency = Function::recip(Function::ms_between(Date::now(), Function::field("created")), 3.16e-11, 1.0, 1.0);
+//!     let distance = Function::geodist("store", 45.15, -93.85);
+//!     Client::new("http://solr:8983", "collection")
+//!         .select("ipod")
+//!         .edismax_params(&EDisMaxParams::new().boost(&recency))
+//!         .filter_query(FilterQuery::frange(Function::log(Function::field("popularity")), 0..=10))
+//!         .field_list(&FieldList::new().field("id").alias_function("km", &distance))
+//!         .sort(Sort::new().function(&distance, Direction::Asc))
+//!         .run().expect("request failed.");
+//! }
+//!
+//! let price = Function::if_else(Function::exists(Function::field("sale_price")), Function::field("sale_price"), Function::field("price"));
+//! assert_eq!(price.to_string(), "if(exists(sale_price),sale_price,price)");
+//! assert_eq!(Function::sum([Function::field("a"), Function::constant(2.5)]).to_string(), "sum(a,2.5)");
+//! ```
+
+use std::fmt::{Display, Formatter};
+use crate::query::Date;
+
+/// A function query expression.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Function {
+    /// Value of a field, by its name.
+    Field(String),
+    /// Number, non finite values render as the division Solr evaluates to them.
+    Constant(f64),
+    /// String constant, rendered quoted.
+    Text(String),
+    /// Dereferenced request parameter, rendered as `$name`.
+    Param(String),
+    Date(Date),
+    Call { name: String, args: Vec<Function> }
+}
+
+impl Function {
+
+    pub fn field(field: &str) -> Self {
+        Function::Field(field.to_owned())
+    }
+
+    pub fn constant(value: f64) -> Self {
+        Function::Constant(value)
+    }
+
+    pub fn text(text: &str) -> Self {
+        Function::Text(text.to_owned())
+    }
+
+    pub fn param(param: &str) -> Self {
+        Function::Param(param.to_owned())
+    }
+
+    /// Any function by its name, i.e. one not having a shorthand.
+    pub fn call<I: IntoIterator<Item = F>, F: Into<Function>>(name: &str, args: I) -> Self {
+        Function::Call { name: name.to_owned(), args: args.into_iter().map(Into::into).collect() }
+    }
+
+    pub fn sum<I: IntoIterator<Item = F>, F: Into<Function>>(args: I) -> Self {
+        Function::call("sum", args)
+    }
+
+    pub fn product<I: IntoIterator<Item = F>, F: Into<Function>>(args: I) -> Self {
+        Function::call("product", args)
+    }
+
+    pub fn sub<A: Into<Function>, B: Into<Function>>(a: A, b: B) -> Self {
+        Function::call("sub", [a.into(), b.into()])
+    }
+
+    pub fn div<A: Into<Function>, B: Into<Function>>(dividend: A, divisor: B) -> Self {
+        Function::call("div", [dividend.into(), divisor.into()])
+    }
+
+    pub fn pow<A: Into<Function>, B: Into<Function>>(base: A, exponent: B) -> Self {
+        Function::call("pow", [base.into(), exponent.into()])
+    }
+
+    /// Base 10 logarithm.
+    pub fn log<A: Into<Function>>(value: A) -> Self {
+        Function::call("log", [value.into()])
+    }
+
+    /// `m / (x * a + b)`, i.e. a recency boost with `ms`.
+    pub fn recip<X: Into<Function>>(x: X, m: f64, a: f64, b: f64) -> Self {
+        Function::call("recip", [x.into(), m.into(), a.into(), b.into()])
+    }
+
+    /// Milliseconds since the epoch of a date or date field.
+    pub fn ms<A: Into<Function>>(date: A) -> Self {
+        Function::call("ms", [date.into()])
+    }
+
+    /// Milliseconds between two dates or date fields, `a - b`.
+    pub fn ms_between<A: Into<Function>, B: Into<Function>>(a: A, b: B) -> Self {
+        Function::call("ms", [a.into(), b.into()])
+    }
+
+    pub fn if_else<C: Into<Function>, T: Into<Function>, E: Into<Function>>(condition: C, then: T, otherwise: E) -> Self {
+        Function::call("if", [condition.into(), then.into(), otherwise.into()])
+    }
+
+    pub fn exists<A: Into<Function>>(value: A) -> Self {
+        Function::call("exists", [value.into()])
+    }
+
+    /// The value, or the default if the value doesn't exist.
+    pub fn def<A: Into<Function>, D: Into<Function>>(value: A, default: D) -> Self {
+        Function::call("def", [value.into(), default.into()])
+    }
+
+    /// How many times the term occurs in the field of the document.
+    pub fn termfreq(field: &str, term: &str) -> Self {
+        Function::call("termfreq", [Function::field(field), Function::text(term)])
+    }
+
+    /// Score of the query in the request parameter, i.e. `query($qq)`.
+    pub fn query(param: &str) -> Self {
+        Function::call("query", [Function::param(param)])
+    }
+
+    /// Score of the query in the request parameter, the default for documents not matching it.
+    pub fn query_or(param: &str, default: f64) -> Self {
+        Function::call("query", [Function::param(param), Function::constant(default)])
+    }
+
+    /// Distance in kilometers of a spatial field from a point.
+    pub fn geodist(field: &str, lat: f64, lon: f64) -> Self {
+        Function::call("geodist", [Function::field(field), lat.into(), lon.into()])
+    }
+
+    /// Distance taking the field and point from the `sfield` and `pt` request parameters.
+    pub fn geodist_params() -> Self {
+        Function::call("geodist", Vec::<Function>::new())
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Function::Field(field) => write!(f, "{}", field),
+            Function::Constant(value) if value.is_nan() => write!(f, "div(0,0)"),
+            Function::Constant(value) if value.is_infinite() => write!(f, "div({},0)", value.signum()),
+            Function::Constant(value) => write!(f, "{}", value),
+            Function::Text(text) => write!(f, "'{}'", text.replace('\\', "\\\\").replace('\'', "\\'")),
+            Function::Param(param) => write!(f, "${}", param),
+            Function::Date(date) => write!(f, "{}", date),
+            Function::Call { name, args } => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "{}({})", name, args.join(","))
+            }
+        }
+    }
+}
+
+impl From<f64> for Function {
+    fn from(value: f64) -> Self {
+        Function::Constant(value)
+    }
+}
+
+impl From<i32> for Function {
+    fn from(value: i32) -> Self {
+        Function::Constant(value.into())
+    }
+}
+
+impl From<Date> for Function {
+    fn from(date: Date) -> Self {
+        Function::Date(date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::DateUnit;
+    use super::*;
+
+    #[test]
+    fn renders_arithmetic() {
+        let function = Function::div(Function::sum([Function::field("a"), Function::field("b")]), Function::pow(Function::field("c"), 2));
+        assert_eq!(function.to_string(), "div(sum(a,b),pow(c,2))");
+        assert_eq!(Function::product([1.5, 2.0]).to_string(), "product(1.5,2)");
+        assert_eq!(Function::sub(Function::log(Function::field("x")), 1).to_string(), "sub(log(x),1)");
+    }
+
+    #[test]
+    fn renders_recency_boost_with_date_math() {
+        let function = Function::recip(Function::ms_between(Date::now().round(DateUnit::Day), Function::field("created")), 3.16e-11, 1.0, 1.0);
+        assert_eq!(function.to_string(), "recip(ms(NOW/DAY,created),0.0000000000316,1,1)");
+        assert_eq!(Function::ms(Function::field("created")).to_string(), "ms(created)");
+    }
+
+    #[test]
+    fn renders_non_finite_constants_as_divisions() {
+        assert_eq!(Function::constant(f64::INFINITY).to_string(), "div(1,0)");
+        assert_eq!(Function::sum([Function::field("a"), f64::NEG_INFINITY.into()]).to_string(), "sum(a,div(-1,0))");
+        assert_eq!(Function::constant(f64::NAN).to_string(), "div(0,0)");
+    }
+
+    #[test]
+    fn renders_conditions_and_defaults() {
+        let function = Function::if_else(Function::exists(Function::field("a")), Function::def(Function::field("b"), 0), 1);
+        assert_eq!(function.to_string(), "if(exists(a),def(b,0),1)");
+    }
+
+    #[test]
+    fn renders_text_quoted() {
+        assert_eq!(Function::termfreq("text", "it's").to_string(), "termfreq(text,'it\\'s')");
+    }
+
+    #[test]
+    fn renders_query_and_geodist() {
+        assert_eq!(Function::query("qq").to_string(), "query($qq)");
+        assert_eq!(Function::query_or("qq", 0.1).to_string(), "query($qq,0.1)");
+        assert_eq!(Function::geodist("store", 45.15, -93.85).to_string(), "geodist(store,45.15,-93.85)");
+        assert_eq!(Function::geodist_params().to_string(), "geodist()");
+    }
+}
@@ -37,6 +37,14 @@ impl HttpClient {
         }
     }
 
+    #[allow(dead_code)]
+    pub fn post_raw_json(&self, query : &str, body: &str) -> Result<Response, Error> {
+        self.reqwest_client.post(query)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_owned())
+            .send()
+    }
+
     #[allow(dead_code)]
     pub fn post_file_reader<'a>(&self, query : &str, file: CloneableFile) -> Result<Response, Error> {
         let length = file.metadata().unwrap().len();
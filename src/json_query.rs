@@ -0,0 +1,439 @@
+//! Solr JSON Query DSL, the structured form of queries in JSON Request API bodies.
+//!
+//! ```rust
+//! use rsolr::Client;
+//! use rsolr::json_query::{BoolQuery, JsonQuery, ParserQuery};
+//! use rsolr::query::{Query, Term};
+//!
+//! fn search() {
+//!     let query = BoolQuery::new()
+//!         .must(ParserQuery::new("ipod charger").param("qf", "title^2 body").edismax())
+//!         .filter(Query::from_term(Term::from_str("electronics").in_field("cat")))
+//!         .must_not("discontinued:true");
+//!     Client::new("http://solr:8983", "collection")
+//!         .request_handler("select")
+//!         .json_query(query)
+//!         .json_filter("inStock:true")
+//!         .run().expect("request failed.");
+//! }
+//!
+//! let json = serde_json::to_value(JsonQuery::from(BoolQuery::new().must("title:rust"))).unwrap();
+//! assert_eq!(json, serde_json::json!({"bool": {"must": ["title:rust"]}}));
+//! ```
+//!
+//! Query trees convert to the DSL: boolean groups become bool nodes, lucene and edismax local
+//! params become parser nodes, anything else the query string. The DSL converts back, query
+//! strings are parsed.
+//!
+//! ```rust
+//! use rsolr::json_query::JsonQuery;
+//! use rsolr::query::{BooleanGroup, Query, Term};
+//!
+//! let group = BooleanGroup::new().must(Term::from_str("rust")).filter(Query::parse("year:[2020 TO *]").unwrap());
+//! let json = JsonQuery::from(Query::from(group.clone()));
+//! assert_eq!(serde_json::to_string(&json).unwrap(), r#"{"bool":{"must":["rust"],"filter":["year:[2020 TO *]"]}}"#);
+//! assert_eq!(Query::try_from(json).unwrap(), Query::from(group));
+//! ```
+
+use std::fmt::Display;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use crate::dismax::EDisMaxParams;
+use crate::error::RSolrError;
+use crate::query::{BooleanGroup, LocalParams, Modifiers, Query, Term};
+
+/// A node of the JSON Query DSL, or a query string in the standard query parser syntax.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonQuery {
+    Bool(BoolQuery),
+    Lucene(ParserQuery),
+    Edismax(ParserQuery),
+    Frange(FrangeQuery),
+    Join(JoinQuery),
+    Knn(KnnQuery),
+    #[serde(untagged)]
+    String(String)
+}
+
+/// Clause lists of a `bool` node, a single clause deserializes as a list of one.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoolQuery {
+    #[serde(skip_serializing_if = "Vec::is_empty", deserialize_with = "one_or_many")]
+    pub must: Vec<JsonQuery>,
+    #[serde(skip_serializing_if = "Vec::is_empty", deserialize_with = "one_or_many")]
+    pub should: Vec<JsonQuery>,
+    #[serde(skip_serializing_if = "Vec::is_empty", deserialize_with = "one_or_many")]
+    pub must_not: Vec<JsonQuery>,
+    #[serde(skip_serializing_if = "Vec::is_empty", deserialize_with = "one_or_many")]
+    pub filter: Vec<JsonQuery>
+}
+
+impl BoolQuery {
+
+    pub fn new() -> Self {
+        BoolQuery::default()
+    }
+
+    pub fn must<Q: Into<JsonQuery>>(mut self, query: Q) -> Self {
+        self.must.push(query.into());
+        self
+    }
+
+    pub fn should<Q: Into<JsonQuery>>(mut self, query: Q) -> Self {
+        self.should.push(query.into());
+        self
+    }
+
+    pub fn must_not<Q: Into<JsonQuery>>(mut self, query: Q) -> Self {
+        self.must_not.push(query.into());
+        self
+    }
+
+    pub fn filter<Q: Into<JsonQuery>>(mut self, query: Q) -> Self {
+        self.filter.push(query.into());
+        self
+    }
+}
+
+/// Query string with the params of its parser, the body of `lucene` and `edismax` nodes.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParserQuery {
+    pub query: String,
+    #[serde(flatten)]
+    pub params: Map<String, Value>
+}
+
+impl ParserQuery {
+
+    pub fn new<Q: Display>(query: Q) -> Self {
+        ParserQuery { query: query.to_string(), params: Map::new() }
+    }
+
+    /// Sets a parser param, i.e. `df` or `qf`.
+    pub fn param<V: Into<Value>>(mut self, key: &str, value: V) -> Self {
+        self.params.insert(key.to_owned(), value.into());
+        self
+    }
+
+    /// Sets the eDisMax params, the repeated ones as lists.
+    pub fn edismax_params(mut self, edismax_params: &EDisMaxParams) -> Self {
+        for (key, value) in edismax_params.params() {
+            match self.params.get_mut(key) {
+                Some(Value::Array(values)) => values.push(value.into()),
+                Some(first) => *first = Value::Array(vec![first.take(), value.into()]),
+                None => {
+                    self.params.insert(key.to_owned(), value.into());
+                }
+            }
+        }
+        self
+    }
+
+    pub fn lucene(self) -> JsonQuery {
+        JsonQuery::Lucene(self)
+    }
+
+    pub fn edismax(self) -> JsonQuery {
+        JsonQuery::Edismax(self)
+    }
+}
+
+/// Function range, matching documents whose function value is between the limits.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FrangeQuery {
+    /// The function, i.e. a function::Function rendered.
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub l: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub u: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub incl: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub incu: Option<bool>
+}
+
+impl FrangeQuery {
+
+    pub fn new<F: Display>(function: F) -> Self {
+        FrangeQuery { query: function.to_string(), ..FrangeQuery::default() }
+    }
+
+    pub fn lower(mut self, lower: f64, inclusive: bool) -> Self {
+        self.l = Some(lower);
+        self.incl = (!inclusive).then_some(false);
+        self
+    }
+
+    pub fn upper(mut self, upper: f64, inclusive: bool) -> Self {
+        self.u = Some(upper);
+        self.incu = (!inclusive).then_some(false);
+        self
+    }
+}
+
+/// Documents joined to the ones matching the query, from a field to another.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JoinQuery {
+    pub from: String,
+    pub to: String,
+    #[serde(rename = "fromIndex", skip_serializing_if = "Option::is_none")]
+    pub from_index: Option<String>,
+    pub query: Box<JsonQuery>
+}
+
+impl JoinQuery {
+
+    pub fn new<Q: Into<JsonQuery>>(from: &str, to: &str, query: Q) -> Self {
+        JoinQuery { from: from.to_owned(), to: to.to_owned(), from_index: None, query: Box::new(query.into()) }
+    }
+
+    /// Joins from another collection.
+    pub fn from_index(mut self, collection: &str) -> Self {
+        self.from_index = Some(collection.to_owned());
+        self
+    }
+}
+
+/// Nearest neighbours of a vector in a dense vector field.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KnnQuery {
+    pub f: String,
+    #[serde(rename = "topK")]
+    pub top_k: u32,
+    /// The vector, i.e. `[1.0,2.5]`.
+    pub query: String
+}
+
+impl KnnQuery {
+
+    pub fn new(field: &str, vector: &[f32], top_k: u32) -> Self {
+        let vector: Vec<String> = vector.iter().map(|value| value.to_string()).collect();
+        KnnQuery { f: field.to_owned(), top_k, query: format!("[{}]", vector.join(",")) }
+    }
+}
+
+impl From<&str> for JsonQuery {
+    fn from(query: &str) -> Self {
+        JsonQuery::String(query.to_owned())
+    }
+}
+
+impl From<String> for JsonQuery {
+    fn from(query: String) -> Self {
+        JsonQuery::String(query)
+    }
+}
+
+impl From<BoolQuery> for JsonQuery {
+    fn from(query: BoolQuery) -> Self {
+        JsonQuery::Bool(query)
+    }
+}
+
+impl From<FrangeQuery> for JsonQuery {
+    fn from(query: FrangeQuery) -> Self {
+        JsonQuery::Frange(query)
+    }
+}
+
+impl From<JoinQuery> for JsonQuery {
+    fn from(query: JoinQuery) -> Self {
+        JsonQuery::Join(query)
+    }
+}
+
+impl From<KnnQuery> for JsonQuery {
+    fn from(query: KnnQuery) -> Self {
+        JsonQuery::Knn(query)
+    }
+}
+
+impl From<Query> for JsonQuery {
+    fn from(query: Query) -> Self {
+        match query {
+            Query::BooleanGroup(group) if group.modifiers == Modifiers::default() && group.minimum_should_match.is_none() => {
+                let convert = |clauses: Vec<Query>| clauses.into_iter().map(JsonQuery::from).collect();
+                JsonQuery::Bool(BoolQuery {
+                    must: convert(group.must),
+                    should: convert(group.should),
+                    must_not: convert(group.must_not),
+                    filter: convert(group.filter)
+                })
+            },
+            Query::LocalParams(local_params, body) => match (local_params.parser(), local_params.literal_params()) {
+                (Some(parser @ ("lucene" | "edismax")), Some(params)) => {
+                    let mut parser_query = ParserQuery::new(&body);
+                    for (key, value) in params {
+                        parser_query = parser_query.param(key, value);
+                    }
+                    match parser {
+                        "lucene" => JsonQuery::Lucene(parser_query),
+                        _ => JsonQuery::Edismax(parser_query)
+                    }
+                },
+                _ => JsonQuery::String(Query::LocalParams(local_params, body).to_string())
+            },
+            query => JsonQuery::String(query.to_string())
+        }
+    }
+}
+
+impl From<&Query> for JsonQuery {
+    fn from(query: &Query) -> Self {
+        JsonQuery::from(query.clone())
+    }
+}
+
+impl TryFrom<JsonQuery> for Query {
+    type Error = RSolrError;
+
+    /// Query strings are parsed, the error tells where they are invalid.
+    fn try_from(query: JsonQuery) -> Result<Self, Self::Error> {
+        Ok(match query {
+            JsonQuery::String(query) => Query::parse(&query)?,
+            JsonQuery::Bool(bool_query) => {
+                let mut group = BooleanGroup::new();
+                group.must = convert_all(bool_query.must)?;
+                group.should = convert_all(bool_query.should)?;
+                group.must_not = convert_all(bool_query.must_not)?;
+                group.filter = convert_all(bool_query.filter)?;
+                Query::BooleanGroup(group)
+            },
+            JsonQuery::Lucene(parser_query) => {
+                let local_params = with_params(LocalParams::new("lucene"), &parser_query.params);
+                Query::parse(&parser_query.query)?.local_params(local_params)
+            },
+            JsonQuery::Edismax(parser_query) => {
+                let local_params = with_params(LocalParams::new("edismax"), &parser_query.params);
+                Query::raw(&parser_query.query).local_params(local_params)
+            },
+            JsonQuery::Frange(frange) => {
+                let mut local_params = LocalParams::new("frange");
+                for (key, value) in [("l", frange.l.map(|l| l.to_string())), ("u", frange.u.map(|u| u.to_string())),
+                                     ("incl", frange.incl.map(|incl| incl.to_string())), ("incu", frange.incu.map(|incu| incu.to_string()))] {
+                    if let Some(value) = value {
+                        local_params = local_params.param(key, &value);
+                    }
+                }
+                Query::raw(&frange.query).local_params(local_params)
+            },
+            JsonQuery::Join(join) => {
+                let mut local_params = LocalParams::new("join").param("from", &join.from).param("to", &join.to);
+                if let Some(from_index) = &join.from_index {
+                    local_params = local_params.param("fromIndex", from_index);
+                }
+                Query::Term(Term::raw("")).local_params(local_params.value(&Query::try_from(*join.query)?.to_string()))
+            },
+            JsonQuery::Knn(knn) => {
+                let local_params = LocalParams::new("knn").param("f", &knn.f).param("topK", &knn.top_k.to_string());
+                Query::raw(&knn.query).local_params(local_params)
+            }
+        })
+    }
+}
+
+fn convert_all(queries: Vec<JsonQuery>) -> Result<Vec<Query>, RSolrError> {
+    queries.into_iter().map(Query::try_from).collect()
+}
+
+/// Lists become repeated params, other values their JSON text unless strings.
+fn with_params(mut local_params: LocalParams, params: &Map<String, Value>) -> LocalParams {
+    for (key, value) in params {
+        let values = match value {
+            Value::Array(values) => values.iter().collect(),
+            value => vec![value]
+        };
+        for value in values {
+            local_params = match value {
+                Value::String(value) => local_params.param(key, value),
+                value => local_params.param(key, &value.to_string())
+            };
+        }
+    }
+    local_params
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<JsonQuery>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        Many(Vec<JsonQuery>),
+        One(JsonQuery)
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::Many(queries) => queries,
+        OneOrMany::One(query) => vec![query]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::dismax::{EDisMaxParams, FieldBoost};
+    use crate::function::Function;
+    use super::*;
+
+    #[test]
+    fn serializes_nodes() {
+        let query = BoolQuery::new()
+            .must(ParserQuery::new("rust").param("df", "title").lucene())
+            .should(FrangeQuery::new(Function::log(Function::field("popularity"))).lower(0.0, true).upper(5.0, false))
+            .must_not(JoinQuery::new("id", "parent_id", "type:draft").from_index("others"))
+            .filter(KnnQuery::new("vector", &[1.0, 2.5], 10));
+        assert_eq!(serde_json::to_value(JsonQuery::from(query)).unwrap(), json!({"bool": {
+            "must": [{"lucene": {"query": "rust", "df": "title"}}],
+            "should": [{"frange": {"query": "log(popularity)", "l": 0.0, "u": 5.0, "incu": false}}],
+            "must_not": [{"join": {"from": "id", "to": "parent_id", "fromIndex": "others", "query": "type:draft"}}],
+            "filter": [{"knn": {"f": "vector", "topK": 10, "query": "[1,2.5]"}}]
+        }}));
+    }
+
+    #[test]
+    fn serializes_edismax_params_with_repeated_ones_as_lists() {
+        let params = EDisMaxParams::new().qf(vec![FieldBoost::boosted("title", 2.0)]).bf("a").bf("b");
+        let query = ParserQuery::new("ipod").edismax_params(&params).edismax();
+        assert_eq!(serde_json::to_value(query).unwrap(), json!({"edismax": {"query": "ipod", "qf": "title^2", "bf": ["a", "b"]}}));
+    }
+
+    #[test]
+    fn deserializes_single_clauses_and_nested_nodes() {
+        let query: JsonQuery = serde_json::from_value(json!({"bool": {"must": "a:b", "filter": [{"lucene": {"query": "c", "q.op": "AND"}}]}})).unwrap();
+        assert_eq!(query, JsonQuery::Bool(BoolQuery::new().must("a:b").filter(ParserQuery::new("c").param("q.op", "AND").lucene())));
+        assert!(serde_json::from_value::<JsonQuery>(json!({"bool": {"must": "a", "unknown": "b"}})).is_err());
+    }
+
+    #[test]
+    fn converts_query_tree() {
+        let nested = BooleanGroup::new().should(Term::from_str("a")).should(Term::from_str("b")).boost(2.0);
+        let group = BooleanGroup::new()
+            .must(Query::from_str("rust").local_params(LocalParams::new("edismax").param("qf", "title body")))
+            .must_not(nested.clone());
+        assert_eq!(JsonQuery::from(Query::from(group)), JsonQuery::Bool(BoolQuery::new()
+            .must(ParserQuery::new("rust").param("qf", "title body").edismax())
+            .must_not(Query::from(nested).to_string())));
+
+        let with_reference = Query::raw("").local_params(LocalParams::new("lucene").deref("v", "qq"));
+        assert_eq!(JsonQuery::from(&with_reference), JsonQuery::String("{!lucene v=$qq}".to_owned()));
+    }
+
+    #[test]
+    fn converts_back_to_query() {
+        let json: JsonQuery = serde_json::from_value(json!({"bool": {
+            "must": {"lucene": {"query": "title:rust", "df": "body"}},
+            "filter": [{"frange": {"query": "log(x)", "l": 1}}, {"join": {"from": "id", "to": "ref", "query": "a:b"}}],
+            "should": {"knn": {"f": "v", "topK": 3, "query": "[1,2]"}}
+        }})).unwrap();
+        assert_eq!(Query::try_from(json).unwrap().to_string(),
+            "{!bool must='{!lucene df=body}title:rust' should='{!knn f=v topK=3}[1,2]' filter='{!frange l=1}log(x)' filter='{!join from=id to=ref v=a:b}'}");
+
+        let invalid = JsonQuery::from("title:(rust");
+        assert!(matches!(Query::try_from(invalid), Err(RSolrError::QueryParse { .. })));
+    }
+}
@@ -64,6 +64,28 @@
 //! }
 //! ```
 //!
+//! ## Optimistic concurrency
+//!
+//! The document is read by realtime get, changed and written back only if nobody changed it meanwhile.
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//! use rsolr::Client;
+//!
+//! #[derive(Serialize, Deserialize, Clone, Default)]
+//! struct Product {
+//!     id: String,
+//!     stock: u32
+//! }
+//!
+//! fn sell() {
+//!     Client::new("http://solr:8983", "collection")
+//!         .retry_version_conflicts(5)
+//!         .update_versioned("product1", |product: Product| Product { stock: product.stock - 1, ..product })
+//!         .expect("request failed.");
+//! }
+//! ```
+//!
 //! ## Custom handler with params
 //!
 //! You can define any handlers as well.
@@ -113,6 +135,7 @@
 //! }
 //! ```
 
+use std::fmt::Display;
 use std::fs::File;
 use std::ops::Deref;
 use cloneable_file::CloneableFile;
@@ -128,14 +151,32 @@ use url::Url;
 #[double]
 use http_client::HttpClient;
 
+use crate::atomic_update::AtomicUpdate;
 use crate::cursor::Cursor;
+use crate::dismax::{DisMaxParams, EDisMaxParams};
 use crate::error::RSolrError;
-use crate::solr_response::SolrResponse;
+use crate::field_list::FieldList;
+use crate::json_query::JsonQuery;
+use crate::lint::{Lint, Linter, Severity};
+use crate::filter_query::FilterQuery;
+use crate::query::RangeValue;
+use crate::solr_response::{SolrResponse, UpdateResponse};
+use crate::update::{CommitOptions, DeleteById, UpdateBatch};
 
 pub mod error;
 pub mod solr_response;
 pub mod query;
 pub mod cursor;
+pub mod filter_query;
+pub mod field_list;
+pub mod sort;
+pub mod dismax;
+pub mod atomic_update;
+pub mod update;
+pub mod function;
+pub mod json_query;
+pub mod lint;
+pub mod macros;
 mod facet_fields;
 mod http_client;
 
@@ -143,6 +184,8 @@ mod http_client;
 #[derive(Clone, Debug)]
 pub enum Payload {
     JsonBody(Value),
+    /// JSON which a Value cannot hold, i.e. update commands with repeated keys.
+    RawJsonBody(String),
     CsvBody(CloneableFile),
     Empty,
     None
@@ -156,22 +199,30 @@ impl RequestHandlers {
     pub const UPLOAD_JSON: &'static str = "update/json/docs";
     pub const UPLOAD_CSV: &'static str = "update/csv";
     pub const DELETE: &'static str = "update";
+    pub const UPDATE: &'static str = "update";
+    pub const REALTIME_GET: &'static str = "get";
 }
 
+pub const DEFAULT_VERSION_CONFLICT_RETRIES: u32 = 3;
+pub const DEFAULT_DELETE_CHUNK_SIZE: usize = 1000;
+
 #[derive(Clone, Debug)]
 pub struct Client<'a> {
     request_handler: &'a str,
     url: Url,
     payload: Payload,
     collection: &'a str,
-    response: Option<Value>
+    response: Option<Value>,
+    lint_policy: Option<(Linter, Severity)>,
+    version_conflict_retries: u32,
+    delete_chunk_size: usize
 }
 
 impl<'a> Client<'a> {
 
     pub fn new(base_url: &str, collection: &'a str) -> Self {
         let url = Url::parse(base_url).unwrap();
-        Client { request_handler: "", url, payload: Payload::None, collection, response: None }
+        Client { request_handler: "", url, payload: Payload::None, collection, response: None, lint_policy: None, version_conflict_retries: DEFAULT_VERSION_CONFLICT_RETRIES, delete_chunk_size: DEFAULT_DELETE_CHUNK_SIZE }
     }
 
     /// Adds custom GET query parameter to the Solr query.
@@ -199,6 +250,23 @@ impl<'a> Client<'a> {
         self
     }
 
+    /// Range facet over a field, start and end take numbers or dates, gap i.e. `Date::day(1)` or `10`.
+    pub fn facet_range<S: RangeValue, E: RangeValue, G: Display>(&mut self, field: &str, start: S, end: E, gap: G) -> &mut Self {
+        self.switch_on_facet();
+        self.url
+            .query_pairs_mut()
+            .append_pair("facet.range", field)
+            .append_pair(&format!("f.{}.facet.range.start", field), &start.to_range_value())
+            .append_pair(&format!("f.{}.facet.range.end", field), &end.to_range_value())
+            .append_pair(&format!("f.{}.facet.range.gap", field), &gap.to_string());
+        self
+    }
+
+    /// Sets the `TZ` parameter, the time zone of date math rounding, i.e. `America/New_York`.
+    pub fn time_zone(&mut self, time_zone: &str) -> &mut Self {
+        self.add_query_param("TZ", time_zone)
+    }
+
     /// Sets the Solr request handler in the URL. You can use RequestHandlers const, but it might be any string.
     pub fn request_handler(&mut self, handler: &'a str) -> &mut Self {
         self.request_handler = handler;
@@ -215,6 +283,25 @@ impl<'a> Client<'a> {
         self.add_query_param("commit", "true")
     }
 
+    /// Shorthand for commitWithin, the changes of the update request are committed within the milliseconds.
+    pub fn commit_within(&mut self, milliseconds: u32) -> &mut Self {
+        self.add_query_param("commitWithin", &milliseconds.to_string())
+    }
+
+    /// Shorthand for versions=true, the update response echoes the adds and deletes with their versions.
+    pub fn versions(&mut self) -> &mut Self {
+        self.add_query_param("versions", "true")
+    }
+
+    /// Commits the update request with the options, i.e. soft commit instead of a hard one.
+    pub fn commit_options(&mut self, options: &CommitOptions) -> &mut Self {
+        self.auto_commit();
+        for (key, value) in options.params() {
+            self.add_query_param(key, &value.to_string());
+        }
+        self
+    }
+
     /// Shorthand for 'start' parameter of Solr basic pagination.
     pub fn start(&mut self, start: u32) -> &mut Self {
         self.add_query_param("start", &start.to_string())
@@ -235,12 +322,12 @@ impl<'a> Client<'a> {
         self
     }
 
-    /// Shorthand for 'sort' parameter.
-    pub fn sort(&mut self, sort: &str) -> &mut Self {
-        self.add_query_param("sort", sort)
+    /// Shorthand for 'sort' parameter, accepts a raw string or a sort::Sort.
+    pub fn sort<S: Display>(&mut self, sort: S) -> &mut Self {
+        self.add_query_param("sort", &sort.to_string())
     }
 
-    /// Request cursor from Solr instance.
+    /// Request cursor from Solr instance. The sort must contain the uniqueKey, see Sort::with_tiebreaker.
     pub fn cursor(&mut self) -> &mut Self {
         self.add_query_param("cursorMark", "*")
     }
@@ -250,9 +337,23 @@ impl<'a> Client<'a> {
         self.add_query_param("rows", &rows.to_string())
     }
 
-    /// Shorthand for 'q' parameter for setting query in the request.
-    pub fn query(&mut self, query: &str) -> &mut Self {
-        self.add_query_param("q", query)
+    /// Shorthand for 'q' parameter for setting query in the request, accepts a raw string or a query::Query.
+    pub fn query<Q: Display>(&mut self, query: Q) -> &mut Self {
+        self.add_query_param("q", &query.to_string())
+    }
+
+    /// Shorthand for 'fq' parameter, accepts a raw string, a query::Query or a FilterQuery with local params.
+    pub fn filter_query<F: Into<FilterQuery>>(&mut self, filter_query: F) -> &mut Self {
+        self.add_query_param("fq", &filter_query.into().to_string())
+    }
+
+    /// Shorthand for 'fl' parameter, adds the parameters the field list requires as well.
+    pub fn field_list(&mut self, field_list: &FieldList) -> &mut Self {
+        self.add_query_param("fl", &field_list.to_string());
+        for (key, value) in field_list.params() {
+            self.add_query_param(key, value);
+        }
+        self
     }
 
     /// Shorthand for 'df' parameter.
@@ -270,6 +371,26 @@ impl<'a> Client<'a> {
         self.payload(Payload::JsonBody(serde_json::to_value::<P>(document).unwrap()))
     }
 
+    /// Sets the query of the JSON Request API body, a json_query::JsonQuery, a query::Query or a string.
+    /// A payload that is not a JSON object (none, empty or a document array) is replaced by a new body.
+    pub fn json_query<Q: Into<JsonQuery>>(&mut self, query: Q) -> &mut Self {
+        let query = serde_json::to_value(query.into()).unwrap();
+        self.json_request_body().insert("query".to_owned(), query);
+        self
+    }
+
+    /// Adds a filter to the JSON Request API body, a single filter already in the body is kept.
+    /// A payload that is not a JSON object (none, empty or a document array) is replaced by a new body.
+    pub fn json_filter<Q: Into<JsonQuery>>(&mut self, filter: Q) -> &mut Self {
+        let filter = serde_json::to_value(filter.into()).unwrap();
+        let filters = self.json_request_body().entry("filter").or_insert_with(|| json!([]));
+        match filters {
+            Value::Array(filters) => filters.push(filter),
+            single => *single = json!([single.take(), filter])
+        }
+        self
+    }
+
     /// Empties the payload, it requires for POST requests (i.e. Solr delete or commit).
     pub fn set_empty_payload(&mut self) -> &mut Self {
         self.payload(Payload::Empty)
@@ -280,10 +401,31 @@ impl<'a> Client<'a> {
         self.payload(Payload::None)
     }
 
+    /// Rejects requests having lints of the severity or above in run with a validation error.
+    pub fn lint_policy(&mut self, linter: Linter, reject_at: Severity) -> &mut Self {
+        self.lint_policy = Some((linter, reject_at));
+        self
+    }
+
+    /// Lints the request parameters with the linter of the lint policy, or the default one.
+    pub fn lint(&self) -> Vec<Lint> {
+        let default = Linter::default();
+        let linter = self.lint_policy.as_ref().map(|(linter, _)| linter).unwrap_or(&default);
+        let params: Vec<(String, String)> = self.url.query_pairs().into_owned().collect();
+        linter.lint_params(params.iter().map(|(key, value)| (key.as_str(), value.as_str())))
+    }
+
     /// Runs the prepared request and fetches response to the type specified. Responds a Result which contains SolrResult, the response part of Solr response.
     pub fn run(&mut self) -> Result<Option<Cursor>, RSolrError> {
+        if let Some((_, reject_at)) = &self.lint_policy {
+            let rejected: Vec<String> = self.lint().into_iter().filter(|lint| lint.severity >= *reject_at).map(|lint| lint.to_string()).collect();
+            if !rejected.is_empty() {
+                return Err(RSolrError::Validation(format!("request rejected by lint policy, {}", rejected.join("; "))));
+            }
+        }
         let http_result = match &self.payload {
             Payload::JsonBody(body) => HttpClient::new().post_json(self.url_str(), Some(body)),
+            Payload::RawJsonBody(body) => HttpClient::new().post_raw_json(self.url_str(), body),
             Payload::Empty => HttpClient::new().post_json(self.url_str(), None),
             Payload::None => HttpClient::new().get(self.url_str()),
             Payload::CsvBody(file) => HttpClient::new().post_file_reader(self.url_str(), file.to_owned())
@@ -311,6 +453,13 @@ impl<'a> Client<'a> {
                 }
             },
             StatusCode::NOT_FOUND => Err(RSolrError::NotFound),
+            StatusCode::CONFLICT => {
+                let body_text = http_response.text().unwrap_or_default();
+                match serde_json::from_str::<Value>(&body_text).ok().and_then(|r| r["error"]["msg"].as_str().map(str::to_owned)) {
+                    Some(message) => Err(RSolrError::VersionConflict(message)),
+                    None => Err(RSolrError::VersionConflict(body_text))
+                }
+            },
             other_status => {
                 let body_text = http_response.text().unwrap();
                 match serde_json::from_str::<Value>(&body_text) {
@@ -334,8 +483,16 @@ impl<'a> Client<'a> {
         }
     }
 
-    /// Shorthand for query.
-    pub fn select(&mut self, query: &str) -> &mut Self {
+    /// Get the response of an update request.
+    pub fn get_update_response(&self) -> Result<UpdateResponse, RSolrError> {
+        match &self.response {
+            Some(v) => serde_json::from_value(v.to_owned()).map_err(|e| RSolrError::Serialization(e.to_string())),
+            None => Ok(UpdateResponse::default())
+        }
+    }
+
+    /// Shorthand for query, accepts a raw string or a query::Query.
+    pub fn select<Q: Display>(&mut self, query: Q) -> &mut Self {
         self
             .request_handler(RequestHandlers::QUERY)
             .query(query)
@@ -354,6 +511,75 @@ impl<'a> Client<'a> {
             .set_json_document::<P>(document)
     }
 
+    /// Sends atomic updates of documents in one batch.
+    pub fn atomic_update<I: IntoIterator<Item = AtomicUpdate>>(&mut self, updates: I) -> &mut Self {
+        let updates: Vec<AtomicUpdate> = updates.into_iter().collect();
+        self
+            .request_handler(RequestHandlers::UPDATE)
+            .set_json_document(updates)
+    }
+
+    /// Sends the update commands in one request, in order.
+    pub fn update_batch(&mut self, batch: &UpdateBatch) -> &mut Self {
+        self
+            .request_handler(RequestHandlers::UPDATE)
+            .payload(Payload::RawJsonBody(batch.to_string()))
+    }
+
+    /// Shorthand for realtime get of a document by id, it is in the doc of the response even if not committed yet.
+    pub fn realtime_get(&mut self, id: &str) -> &mut Self {
+        self
+            .request_handler(RequestHandlers::REALTIME_GET)
+            .add_query_param("id", id)
+    }
+
+    /// Number of retries of update_versioned on version conflicts.
+    pub fn retry_version_conflicts(&mut self, retries: u32) -> &mut Self {
+        self.version_conflict_retries = retries;
+        self
+    }
+
+    /// Reads the document by realtime get, applies the change and writes it back only if its `_version_` did not change meanwhile.
+    /// On version conflict it starts over, see retry_version_conflicts. Responds the written document.
+    /// Both requests carry the query params set before, i.e. commitWithin.
+    /// The changed fields of T are sent as an atomic update of the document with the uniqueKey `id`, so stored fields
+    /// T does not declare stay; without changes nothing is written.
+    pub fn update_versioned<T, F>(&mut self, id: &str, mut change: F) -> Result<T, RSolrError>
+    where T: Serialize + for<'de> Deserialize<'de> + Clone + Default, F: FnMut(T) -> T {
+        let query = self.url.query().map(str::to_owned);
+        let mut retries = 0;
+        loop {
+            self.url.set_query(query.as_deref());
+            self.realtime_get(id).run()?;
+            let document = match self.get_response::<Value>()?.doc {
+                Some(Value::Object(document)) => document,
+                _ => return Err(RSolrError::NotFound)
+            };
+            let version = match document.get("_version_").and_then(Value::as_i64) {
+                Some(version) => version,
+                None => return Err(RSolrError::Validation(format!("document `{}` has no _version_", id)))
+            };
+            let current = serde_json::from_value::<T>(Value::Object(document)).map_err(|e| RSolrError::Serialization(e.to_string()))?;
+            let before = document_fields(&current)?;
+            let changed = change(current);
+            let after = document_fields(&changed)?;
+            let mut changes: Vec<(&String, &Value)> = after.iter().filter(|(field, value)| before.get(*field) != Some(*value)).collect();
+            changes.extend(before.keys().filter(|field| !after.contains_key(*field)).map(|field| (field, &Value::Null)));
+            if changes.is_empty() {
+                return Ok(changed)
+            }
+            let update = changes.into_iter().fold(AtomicUpdate::new(id), |update, (field, value)| update.set(field, value));
+
+            self.url.set_query(query.as_deref());
+            let written = self.atomic_update([update.version(version)]).run().map(|_| ());
+            match written {
+                Ok(()) => return Ok(changed),
+                Err(RSolrError::VersionConflict(_)) if retries < self.version_conflict_retries => retries += 1,
+                Err(e) => return Err(e)
+            }
+        }
+    }
+
     /// Shorthand for uploading a CSV file.
     pub fn upload_csv(&mut self, file: File) -> &mut Self {
         self
@@ -379,6 +605,41 @@ impl<'a> Client<'a> {
             .set_json_document(delete_payload)
     }
 
+    /// Shorthand for delete by id, accepts an id or an update::DeleteById with route or version.
+    pub fn delete_by_id<D: Into<DeleteById>>(&mut self, id: D) -> &mut Self {
+        self.update_batch(&UpdateBatch::new().delete_ids([id]))
+    }
+
+    /// Number of ids deleted in one request by delete_chunked.
+    pub fn delete_chunk_size(&mut self, chunk_size: usize) -> &mut Self {
+        self.delete_chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Deletes by ids in chunks of delete_chunk_size, one request each with the query params set before.
+    /// Like update_versioned it sends the requests itself, no run needed. Responds the update responses of the
+    /// chunks, without ids it sends nothing. If a chunk fails after others were deleted, the error is
+    /// RSolrError::PartialDelete with the update responses of the deleted chunks.
+    pub fn delete_chunked<I: IntoIterator<Item = D>, D: Into<DeleteById>>(&mut self, ids: I) -> Result<Vec<UpdateResponse>, RSolrError> {
+        let ids: Vec<DeleteById> = ids.into_iter().map(Into::into).collect();
+        if ids.is_empty() {
+            return Ok(vec![])
+        }
+        let query = self.url.query().map(str::to_owned);
+        let mut responses = vec![];
+        for chunk in ids.chunks(self.delete_chunk_size) {
+            self.url.set_query(query.as_deref());
+            let deleted = self.update_batch(&UpdateBatch::new().delete_ids(chunk.to_vec())).run().map(|_| ())
+                .and_then(|_| self.get_update_response());
+            match deleted {
+                Ok(response) => responses.push(response),
+                Err(source) if responses.is_empty() => return Err(source),
+                Err(source) => return Err(RSolrError::PartialDelete { deleted: responses, source: Box::new(source) })
+            }
+        }
+        Ok(responses)
+    }
+
     /// Shorthand for direct commit.
     pub fn commit(&mut self) -> &mut Self {
         self
@@ -387,6 +648,30 @@ impl<'a> Client<'a> {
             .set_empty_payload()
     }
 
+    /// Shorthand for direct commit with options.
+    pub fn commit_with(&mut self, options: &CommitOptions) -> &mut Self {
+        self
+            .request_handler(RequestHandlers::UPDATE)
+            .commit_options(options)
+            .set_empty_payload()
+    }
+
+    /// Shorthand for optimize, merging the index into at most max_segments segments, one if not set.
+    pub fn optimize(&mut self, max_segments: Option<u32>) -> &mut Self {
+        self
+            .request_handler(RequestHandlers::UPDATE)
+            .add_query_param("optimize", "true");
+        if let Some(max_segments) = max_segments {
+            self.add_query_param("maxSegments", &max_segments.to_string());
+        }
+        self.set_empty_payload()
+    }
+
+    /// Shorthand for a commit merging away the segments with deleted documents, cheaper than optimize.
+    pub fn expunge_deletes(&mut self) -> &mut Self {
+        self.commit_with(&CommitOptions::new().expunge_deletes(true))
+    }
+
     /// Shorthand for setting dismax query parser.
     pub fn dismax(&mut self) -> &mut Self {
         self.add_query_param("defType", "dismax")
@@ -397,6 +682,35 @@ impl<'a> Client<'a> {
         self.add_query_param("defType", "edismax")
     }
 
+    /// Sets dismax query parser with its params.
+    pub fn dismax_params(&mut self, params: &DisMaxParams) -> &mut Self {
+        self.dismax();
+        for (key, value) in params.params() {
+            self.add_query_param(key, &value);
+        }
+        self
+    }
+
+    /// Sets edismax query parser with its params.
+    pub fn edismax_params(&mut self, params: &EDisMaxParams) -> &mut Self {
+        self.edismax();
+        for (key, value) in params.params() {
+            self.add_query_param(key, &value);
+        }
+        self
+    }
+
+    /// The JSON body as object, other payloads are replaced by an empty one.
+    fn json_request_body(&mut self) -> &mut serde_json::Map<String, Value> {
+        if !matches!(&self.payload, Payload::JsonBody(Value::Object(_))) {
+            self.payload = Payload::JsonBody(json!({}));
+        }
+        match &mut self.payload {
+            Payload::JsonBody(Value::Object(body)) => body,
+            _ => unreachable!("the payload is a JSON object")
+        }
+    }
+
     fn switch_on_facet(&mut self) {
         for query_pair in self.url.query_pairs() {
             if query_pair.0 == "facet" && query_pair.1 == "on" {
@@ -412,9 +726,19 @@ impl<'a> Client<'a> {
     }
 }
 
+/// The fields of a document, it has to serialize to a JSON object.
+fn document_fields<T: Serialize>(document: &T) -> Result<serde_json::Map<String, Value>, RSolrError> {
+    match serde_json::to_value(document) {
+        Ok(Value::Object(fields)) => Ok(fields),
+        Ok(_) => Err(RSolrError::Serialization("the document is not a JSON object".to_owned())),
+        Err(e) => Err(RSolrError::Serialization(e.to_string()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::sync::{Mutex, MutexGuard};
+    use std::sync::{Arc, Mutex, MutexGuard};
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     use mockall::lazy_static;
     use mockall::predicate::eq;
@@ -469,6 +793,51 @@ mod tests {
         assert_eq!(url_string, "http://host:8983/solr/collection/request_handler?commit=true");
     }
 
+    #[test]
+    fn build_a_url_from_query_tree() {
+        let mut params = Client::new("http://host:8983", "collection");
+        params.select(query::Query::from_term(query::Term::from_str("rust").in_field("title")).or().term(query::Term::from_str("solr")));
+
+        let url_string = params.url_str();
+        assert_eq!(url_string, "http://host:8983/solr/collection/select?q=title%3Arust+OR+solr");
+    }
+
+    #[test]
+    fn build_a_url_with_commit_within_and_commit_options() {
+        let mut client = Client::new("http://host:8983", "collection");
+        client
+            .upload_json(json!({"id": "1"}))
+            .commit_within(10000);
+        assert_eq!(client.url_str(), "http://host:8983/solr/collection/update%2Fjson%2Fdocs?commitWithin=10000");
+
+        let mut client = Client::new("http://host:8983", "collection");
+        client
+            .upload_json(json!({"id": "1"}))
+            .commit_options(&update::CommitOptions::new().soft_commit(true).wait_searcher(false));
+        assert_eq!(client.url_str(), "http://host:8983/solr/collection/update%2Fjson%2Fdocs?commit=true&softCommit=true&waitSearcher=false");
+    }
+
+    #[test]
+    fn build_commit_optimize_and_expunge_deletes_requests() {
+        let mut client = Client::new("http://host:8983", "collection");
+        client.commit_with(&update::CommitOptions::new().open_searcher(false));
+        assert_eq!(client.url_str(), "http://host:8983/solr/collection/update?commit=true&openSearcher=false");
+        assert!(matches!(client.payload, Payload::Empty));
+
+        let mut client = Client::new("http://host:8983", "collection");
+        client.optimize(Some(4));
+        assert_eq!(client.url_str(), "http://host:8983/solr/collection/update?optimize=true&maxSegments=4");
+        assert!(matches!(client.payload, Payload::Empty));
+
+        let mut client = Client::new("http://host:8983", "collection");
+        client.optimize(None);
+        assert_eq!(client.url_str(), "http://host:8983/solr/collection/update?optimize=true");
+
+        let mut client = Client::new("http://host:8983", "collection");
+        client.expunge_deletes();
+        assert_eq!(client.url_str(), "http://host:8983/solr/collection/update?commit=true&expungeDeletes=true");
+    }
+
     #[test]
     fn build_a_url_with_start_and_rows() {
         let mut params = Client::new("http://host:8983", "collection");
@@ -492,6 +861,80 @@ mod tests {
         assert_eq!(url_string, "http://host:8983/solr/collection/request_handler?df=defaultfield");
     }
 
+    #[test]
+    fn build_a_url_with_filter_queries() {
+        let mut params = Client::new("http://host:8983", "collection");
+        params
+            .request_handler("request_handler")
+            .filter_query("inStock:true")
+            .filter_query(FilterQuery::new("type:book").tag("type").cache(false));
+
+        let url_string = params.url_str();
+        assert_eq!(url_string, "http://host:8983/solr/collection/request_handler?fq=inStock%3Atrue&fq=%7B%21cache%3Dfalse+tag%3Dtype%7Dtype%3Abook");
+    }
+
+    #[test]
+    fn build_a_url_with_field_list() {
+        let mut params = Client::new("http://host:8983", "collection");
+        params
+            .request_handler("request_handler")
+            .field_list(&FieldList::new().field("id").score().subquery("sub", "*:*"));
+
+        let url_string = params.url_str();
+        assert_eq!(url_string, "http://host:8983/solr/collection/request_handler?fl=id%2Cscore%2Csub%3A%5Bsubquery%5D&sub.q=*%3A*");
+    }
+
+    #[test]
+    fn build_a_url_with_sort() {
+        let mut params = Client::new("http://host:8983", "collection");
+        params
+            .request_handler("request_handler")
+            .sort(sort::Sort::new().desc("price").with_tiebreaker("id"));
+
+        let url_string = params.url_str();
+        assert_eq!(url_string, "http://host:8983/solr/collection/request_handler?sort=price+desc%2Cid+asc");
+    }
+
+    #[test]
+    fn lint_policy_rejects_requests_above_the_threshold() {
+        let mut client = Client::new("http://host:8983", "collection");
+        client
+            .lint_policy(lint::Linter::new().unique_key("sku"), lint::Severity::Warning)
+            .select("name:*x")
+            .sort("id asc")
+            .cursor();
+        assert_eq!(client.lint().len(), 2);
+
+        let result = client.run();
+        assert!(matches!(result, Err(RSolrError::Validation(message)) if message == "request rejected by lint policy, warning: leading wildcard `*x` in `name`; error: cursorMark requires sorting by `sku`"));
+    }
+
+    #[test]
+    fn build_a_url_with_date_range_facet_and_time_zone() {
+        let mut params = Client::new("http://host:8983", "collection");
+        params
+            .request_handler("request_handler")
+            .facet_range("created", query::Date::now().round(query::DateUnit::Day).minus(query::Date::day(7)), query::Date::now(), query::Date::day(1))
+            .time_zone("Europe/Budapest");
+
+        let url_string = params.url_str();
+        assert_eq!(url_string, "http://host:8983/solr/collection/request_handler?facet=on&facet.range=created&f.created.facet.range.start=NOW%2FDAY-7DAYS&f.created.facet.range.end=NOW&f.created.facet.range.gap=%2B1DAYS&TZ=Europe%2FBudapest");
+    }
+
+    #[test]
+    fn build_a_url_with_edismax_params() {
+        let mut params = Client::new("http://host:8983", "collection");
+        params
+            .request_handler("request_handler")
+            .edismax_params(&dismax::EDisMaxParams::new()
+                .qf(vec![dismax::FieldBoost::boosted("title", 2.0), dismax::FieldBoost::new("body")])
+                .mm("2<-25%".parse().unwrap())
+                .sow(false));
+
+        let url_string = params.url_str();
+        assert_eq!(url_string, "http://host:8983/solr/collection/request_handler?defType=edismax&qf=title%5E2+body&mm=2%3C-25%25&sow=false");
+    }
+
     #[test]
     fn url_built_with_facet_if_facet_fields_set() {
         let mut params = Client::new("http://host:8983", "collection");
@@ -671,6 +1114,193 @@ mod tests {
         assert_eq!(command.get_response::<Value>().unwrap().response.unwrap().docs[0]["success"], true);
     }
 
+    #[test]
+    fn run_posts_atomic_updates_in_one_batch() {
+        let _m = get_lock(&MTX);
+
+        let ctx = HttpClient::new_context();
+        ctx.expect().returning(|| {
+            let mut mock = HttpClient::default();
+            mock.expect_post_json()
+                .withf(| url, body | url == "http://localhost:8983/solr/default/update?commit=true" && *body == Some(&json!([
+                    {"id": "1", "price": {"set": 10}},
+                    {"id": "2", "tags": {"add-distinct": "new"}, "_version_": 5}
+                ])))
+                .returning(|_, _| Ok(reqwest::blocking::Response::from(http::response::Builder::new()
+                    .status(200)
+                    .body(r#"{"responseHeader": {"status": 0, "QTime": 3}}"#)
+                    .unwrap())));
+            mock
+        });
+
+        let mut client = Client::new("http://localhost:8983", "default");
+        let result = client
+            .atomic_update([
+                AtomicUpdate::new("1").set("price", 10),
+                AtomicUpdate::new("2").add_distinct("tags", "new").version(5)
+            ])
+            .auto_commit()
+            .run();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn run_posts_update_batch_as_raw_json() {
+        let _m = get_lock(&MTX);
+
+        let ctx = HttpClient::new_context();
+        ctx.expect().returning(|| {
+            let mut mock = HttpClient::default();
+            mock.expect_post_raw_json()
+                .withf(| url, body | url == "http://localhost:8983/solr/default/update?versions=true" && body == r#"{"add":{"doc":{"id":"1"}},"add":{"doc":{"id":"2"}},"delete":["3"],"commit":{"softCommit":true}}"#)
+                .returning(|_, _| Ok(reqwest::blocking::Response::from(http::response::Builder::new()
+                    .status(200)
+                    .body(r#"{"responseHeader": {"status": 0, "QTime": 7, "errors": [{"type": "ADD", "id": "2", "message": "bad"}]}, "adds": ["1", 11]}"#)
+                    .unwrap())));
+            mock
+        });
+
+        let batch = update::UpdateBatch::new()
+            .add(json!({"id": "1"}))
+            .add(json!({"id": "2"}))
+            .delete_ids(["3"])
+            .commit(update::CommitOptions::new().soft_commit(true));
+        let mut client = Client::new("http://localhost:8983", "default");
+        let result = client.update_batch(&batch).versions().run();
+        assert!(result.is_ok());
+        let response = client.get_update_response().unwrap();
+        assert_eq!(response.responseHeader.QTime, 7);
+        assert_eq!(response.adds, vec![solr_response::UpdatedDocument { id: "1".to_owned(), version: 11 }]);
+        assert_eq!(response.errors()[0].id, "2");
+    }
+
+    #[test]
+    fn delete_chunked_sends_chunks_with_the_same_params() {
+        let _m = get_lock(&MTX);
+
+        let ctx = HttpClient::new_context();
+        ctx.expect().returning(|| {
+            let mut mock = HttpClient::default();
+            mock.expect_post_raw_json()
+                .withf(| url, body | url == "http://localhost:8983/solr/default/update?commit=true" && (
+                    body == r#"{"delete":["1",{"_route_":"a!","id":"2"}]}"# || body == r#"{"delete":["3"]}"#))
+                .times(1)
+                .returning(|_, _| Ok(reqwest::blocking::Response::from(http::response::Builder::new()
+                    .status(200)
+                    .body(r#"{"responseHeader": {"status": 0, "QTime": 2}}"#)
+                    .unwrap())));
+            mock
+        });
+
+        let mut client = Client::new("http://localhost:8983", "default");
+        let responses = client
+            .auto_commit()
+            .delete_chunk_size(2)
+            .delete_chunked([update::DeleteById::new("1"), update::DeleteById::new("2").route("a!"), update::DeleteById::new("3")])
+            .unwrap();
+        assert_eq!(responses.len(), 2);
+    }
+
+    #[test]
+    fn delete_chunked_without_ids_sends_nothing() {
+        let _m = get_lock(&MTX);
+        let ctx = HttpClient::new_context();
+        ctx.expect().returning(|| {
+            let mut mock = HttpClient::default();
+            mock.expect_post_raw_json().never();
+            mock
+        });
+
+        let mut client = Client::new("http://localhost:8983", "default");
+        let responses = client.auto_commit().delete_chunked(Vec::<&str>::new()).unwrap();
+        assert!(responses.is_empty());
+        assert_eq!(client.url_str(), "http://localhost:8983/?commit=true");
+    }
+
+    #[test]
+    fn delete_chunked_responds_the_deleted_chunks_on_error() {
+        let _m = get_lock(&MTX);
+        let ctx = HttpClient::new_context();
+        ctx.expect().returning(|| {
+            let mut mock = HttpClient::default();
+            mock.expect_post_raw_json()
+                .returning(|_, body| {
+                    let (status, response) = match body == r#"{"delete":["1","2"]}"# {
+                        true => (200, r#"{"responseHeader": {"status": 0, "QTime": 2}}"#),
+                        false => (400, r#"{"error": {"code": 400, "msg": "bad id"}}"#)
+                    };
+                    Ok(reqwest::blocking::Response::from(http::response::Builder::new().status(status).body(response).unwrap()))
+                });
+            mock
+        });
+
+        let mut client = Client::new("http://localhost:8983", "default");
+        let result = client.delete_chunk_size(2).delete_chunked(["1", "2", "3"]);
+        match result {
+            Err(RSolrError::PartialDelete { deleted, source }) => {
+                assert_eq!(deleted.len(), 1);
+                assert!(matches!(*source, RSolrError::Syntax(message) if message == "bad id"));
+            },
+            _ => panic!("partial delete expected")
+        }
+        let result = client.delete_chunked(["3"]);
+        assert!(matches!(result, Err(RSolrError::Syntax(_))));
+    }
+
+    #[test]
+    fn build_a_delete_by_id_request() {
+        let mut client = Client::new("http://localhost:8983", "default");
+        client.delete_by_id(update::DeleteById::new("1").version(7));
+        assert_eq!(client.url_str(), "http://localhost:8983/solr/default/update");
+        assert!(matches!(&client.payload, Payload::RawJsonBody(body) if body == r#"{"delete":[{"_version_":7,"id":"1"}]}"#));
+    }
+
+    #[test]
+    fn run_posts_json_query_dsl_body() {
+        let _m = get_lock(&MTX);
+
+        let ctx = HttpClient::new_context();
+        ctx.expect().returning(|| {
+            let mut mock = HttpClient::default();
+            mock.expect_post_json()
+                .withf(| url, body | url == "http://localhost:8983/solr/default/select" && *body == Some(&json!({
+                    "query": {"bool": {"must": ["title:rust"], "must_not": ["draft"]}},
+                    "filter": ["inStock:true", {"frange": {"query": "log(popularity)", "l": 1.0}}]
+                })))
+                .returning(|_, _| Ok(reqwest::blocking::Response::from(http::response::Builder::new()
+                    .status(200)
+                    .body(r#"{"response": {"numFound": 1,"numFoundExact": true,"start": 0,"docs": [{"success": true }]}}"#)
+                    .unwrap())));
+            mock
+        });
+
+        let query = query::BooleanGroup::new()
+            .must(query::Term::from_str("rust").in_field("title"))
+            .must_not(query::Term::from_str("draft"));
+        let mut client = Client::new("http://localhost:8983", "default");
+        let result = client
+            .request_handler("select")
+            .json_query(query::Query::from(query))
+            .json_filter("inStock:true")
+            .json_filter(json_query::FrangeQuery::new("log(popularity)").lower(1.0, true))
+            .run();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn json_filter_keeps_a_single_filter_of_the_body() {
+        let mut client = Client::new("http://localhost:8983", "default");
+        client.set_json_document(json!({"filter": "inStock:true"})).json_filter("type:book");
+        assert!(matches!(&client.payload, Payload::JsonBody(body) if *body == json!({"filter": ["inStock:true", "type:book"]})));
+    }
+
+    #[test]
+    fn json_query_replaces_a_payload_that_is_not_an_object() {
+        let mut client = Client::new("http://localhost:8983", "default");
+        client.set_json_document(json!([{"id": 1}])).json_query("*:*");
+        assert!(matches!(&client.payload, Payload::JsonBody(body) if *body == json!({"query": "*:*"})));
+    }
+
     #[test]
     fn select_responds_rsolr_error_with_other_problem_if_dunno() {
         let _m = get_lock(&MTX);
@@ -808,6 +1438,137 @@ mod tests {
         assert!(format!("{:?}", error).contains("some unparseable thing"));
     }
 
+    fn setup_versioned_mock(conflicts: usize, posts: Arc<AtomicUsize>) -> HttpClient {
+        let mut mock = HttpClient::default();
+        mock.expect_get()
+            .with(eq("http://localhost:8983/solr/default/get?id=1"))
+            .returning(|_| Ok(reqwest::blocking::Response::from(http::response::Builder::new()
+                .status(200)
+                .body(r#"{"doc": {"id": "1", "stock": 5, "name": "pen", "_version_": 42}}"#)
+                .unwrap())));
+        mock.expect_post_json()
+            .withf(| url, body | url == "http://localhost:8983/solr/default/update" && *body == Some(&json!([{"id": "1", "stock": {"set": 4}, "_version_": 42}])))
+            .returning(move |_, _| {
+                let status = match posts.fetch_add(1, Ordering::SeqCst) < conflicts {
+                    true => 409,
+                    false => 200
+                };
+                Ok(reqwest::blocking::Response::from(http::response::Builder::new()
+                    .status(status)
+                    .body(r#"{"error": {"code": 409, "msg": "version conflict for 1 expected=42 actual=43"}}"#)
+                    .unwrap()))
+            });
+        mock
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+    struct Stock {
+        id: String,
+        stock: u32
+    }
+
+    #[test]
+    fn run_maps_conflict_to_version_conflict() {
+        let _m = get_lock(&MTX);
+        let posts = Arc::new(AtomicUsize::new(0));
+        let ctx = HttpClient::new_context();
+        ctx.expect().returning(move || setup_versioned_mock(usize::MAX, posts.clone()));
+
+        let result = Client::new("http://localhost:8983", "default")
+            .request_handler(RequestHandlers::UPDATE)
+            .atomic_update([atomic_update::AtomicUpdate::new("1").set("stock", 4).version(42)])
+            .run()
+            .map(|_| ());
+        assert!(matches!(result, Err(RSolrError::VersionConflict(message)) if message == "version conflict for 1 expected=42 actual=43"));
+    }
+
+    #[test]
+    fn update_versioned_retries_on_version_conflict() {
+        let _m = get_lock(&MTX);
+        let posts = Arc::new(AtomicUsize::new(0));
+        let mock_posts = posts.clone();
+        let ctx = HttpClient::new_context();
+        ctx.expect().returning(move || setup_versioned_mock(2, mock_posts.clone()));
+
+        let mut client = Client::new("http://localhost:8983", "default");
+        let result = client.update_versioned("1", |stock: Stock| Stock { stock: stock.stock - 1, ..stock });
+        assert_eq!(result.unwrap(), Stock { id: "1".to_owned(), stock: 4 });
+        assert_eq!(posts.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn update_versioned_gives_up_after_the_retry_limit() {
+        let _m = get_lock(&MTX);
+        let posts = Arc::new(AtomicUsize::new(0));
+        let mock_posts = posts.clone();
+        let ctx = HttpClient::new_context();
+        ctx.expect().returning(move || setup_versioned_mock(usize::MAX, mock_posts.clone()));
+
+        let mut client = Client::new("http://localhost:8983", "default");
+        let result = client
+            .retry_version_conflicts(1)
+            .update_versioned("1", |stock: Stock| Stock { stock: stock.stock - 1, ..stock });
+        assert!(matches!(result, Err(RSolrError::VersionConflict(_))));
+        assert_eq!(posts.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn update_versioned_keeps_the_query_params_set_before() {
+        let _m = get_lock(&MTX);
+        let ctx = HttpClient::new_context();
+        ctx.expect().returning(|| {
+            let mut mock = HttpClient::default();
+            mock.expect_get()
+                .with(eq("http://localhost:8983/solr/default/get?commitWithin=1000&id=1"))
+                .returning(|_| Ok(reqwest::blocking::Response::from(http::response::Builder::new()
+                    .status(200)
+                    .body(r#"{"doc": {"id": "1", "stock": 5, "_version_": 42}}"#)
+                    .unwrap())));
+            mock.expect_post_json()
+                .withf(| url, _ | url == "http://localhost:8983/solr/default/update?commitWithin=1000")
+                .returning(|_, _| Ok(reqwest::blocking::Response::from(http::response::Builder::new()
+                    .status(200)
+                    .body(r#"{"responseHeader": {"status": 0, "QTime": 2}}"#)
+                    .unwrap())));
+            mock
+        });
+
+        let mut client = Client::new("http://localhost:8983", "default");
+        let result = client
+            .add_query_param("commitWithin", "1000")
+            .update_versioned("1", |stock: Stock| Stock { stock: stock.stock - 1, ..stock });
+        assert_eq!(result.unwrap(), Stock { id: "1".to_owned(), stock: 4 });
+    }
+
+    #[test]
+    fn update_versioned_sends_only_the_changed_fields() {
+        let _m = get_lock(&MTX);
+        let posts = Arc::new(AtomicUsize::new(0));
+        let mock_posts = posts.clone();
+        let ctx = HttpClient::new_context();
+        ctx.expect().returning(move || setup_versioned_mock(0, mock_posts.clone()));
+
+        let mut client = Client::new("http://localhost:8983", "default");
+        let result = client.update_versioned("1", |stock: Stock| Stock { stock: stock.stock - 1, ..stock });
+        assert_eq!(result.unwrap(), Stock { id: "1".to_owned(), stock: 4 });
+        assert_eq!(posts.load(Ordering::SeqCst), 1);
+
+        let unchanged = client.update_versioned("1", |stock: Stock| stock);
+        assert_eq!(unchanged.unwrap(), Stock { id: "1".to_owned(), stock: 5 });
+        assert_eq!(posts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn update_versioned_of_missing_document_is_not_found() {
+        let _m = get_lock(&MTX);
+        let ctx = HttpClient::new_context();
+        ctx.expect().returning(|| setup_get_mock("http://localhost:8983/solr/default/get?id=2", 200, r#"{"doc": null}"#));
+
+        let mut client = Client::new("http://localhost:8983", "default");
+        let result = client.update_versioned("2", |stock: Stock| stock);
+        assert!(matches!(result, Err(RSolrError::NotFound)));
+    }
+
     #[test]
     fn run_responds_cursor_if_cursor_set() {
         let _m = get_lock(&MTX);
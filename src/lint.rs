@@ -0,0 +1,268 @@
+//! Linting of requests and queries for expensive or risky constructs.
+//!
+//! ```rust
+//! use rsolr::Client;
+//! use rsolr::lint::{Linter, Rule, Severity};
+//! use rsolr::query::Query;
+//!
+//! let lints = Query::parse("title:*ust").unwrap().lint();
+//! assert_eq!(lints[0].rule, Rule::LeadingWildcard);
+//!
+//! let mut client = Client::new("http://solr:8983", "collection");
+//! client.select("*:*").rows(100_000);
+//! let rules: Vec<Rule> = client.lint().into_iter().map(|lint| lint.rule).collect();
+//! assert_eq!(rules, vec![Rule::LargeRows, Rule::MatchAllWithoutFilter]);
+//! ```
+//!
+//! A client with a lint policy rejects the requests with lints of the threshold severity or above.
+//!
+//! ```rust
+//! use rsolr::Client;
+//! use rsolr::error::RSolrError;
+//! use rsolr::lint::{Linter, Severity};
+//!
+//! let mut client = Client::new("http://solr:8983", "collection");
+//! let result = client
+//!     .lint_policy(Linter::new().max_rows(500).large_fields(&["body"]), Severity::Warning)
+//!     .select("body:/.*error.*/")
+//!     .run();
+//! assert!(matches!(result, Err(RSolrError::Validation(..))));
+//! ```
+
+use std::fmt::{Display, Formatter};
+use crate::query::{Group, Query, Term, TermValue};
+use crate::query::rewrite::Visit;
+
+pub const DEFAULT_MAX_ROWS: u32 = 1000;
+pub const DEFAULT_MAX_START: u32 = 10000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error")
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rule {
+    /// Wildcard at the start of a term, it enumerates every term of the field.
+    LeadingWildcard,
+    /// Regular expression, expensive on fields with many terms.
+    Regex,
+    /// `rows` above the maximum.
+    LargeRows,
+    /// `start` above the maximum, cursors page deeply.
+    DeepPaging,
+    /// `facet.limit=-1`, every facet value is returned.
+    UnlimitedFacet,
+    /// `cursorMark` without sorting by the uniqueKey, Solr rejects it.
+    CursorWithoutUniqueKeySort,
+    /// `q=*:*` without filter queries.
+    MatchAllWithoutFilter
+}
+
+/// A finding of the linter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lint {
+    pub rule: Rule,
+    pub severity: Severity,
+    pub message: String
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+
+/// Lint limits, by default rows up to 1000, start up to 10000, `id` as the uniqueKey and no large fields.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Linter {
+    max_rows: u32,
+    max_start: u32,
+    unique_key: String,
+    large_fields: Vec<String>
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Linter { max_rows: DEFAULT_MAX_ROWS, max_start: DEFAULT_MAX_START, unique_key: "id".to_owned(), large_fields: vec![] }
+    }
+}
+
+impl Linter {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_rows(mut self, max_rows: u32) -> Self {
+        self.max_rows = max_rows;
+        self
+    }
+
+    pub fn max_start(mut self, max_start: u32) -> Self {
+        self.max_start = max_start;
+        self
+    }
+
+    pub fn unique_key(mut self, unique_key: &str) -> Self {
+        self.unique_key = unique_key.to_owned();
+        self
+    }
+
+    /// Fields with many terms, regular expressions on them are warnings instead of infos.
+    pub fn large_fields(mut self, fields: &[&str]) -> Self {
+        self.large_fields = fields.iter().map(|field| field.to_string()).collect();
+        self
+    }
+
+    /// Lints of the terms of the query.
+    pub fn lint_query(&self, query: &Query) -> Vec<Lint> {
+        let mut visitor = QueryLints { linter: self, fields: vec![], lints: vec![] };
+        visitor.visit_query(query);
+        visitor.lints
+    }
+
+    /// Lints of the request parameters, queries which cannot be parsed are not linted.
+    pub fn lint_params<'p, I: IntoIterator<Item = (&'p str, &'p str)>>(&self, params: I) -> Vec<Lint> {
+        let mut lints = vec![];
+        let mut query = None;
+        let mut filtered = false;
+        let mut cursor = false;
+        let mut sort = None;
+        for (key, value) in params {
+            match key {
+                "q" | "fq" => {
+                    if let Ok(parsed) = Query::parse(value) {
+                        lints.extend(self.lint_query(&parsed));
+                    }
+                    match key {
+                        "q" => query = Some(value.trim()),
+                        _ => filtered = true
+                    }
+                },
+                "rows" if value.parse::<u32>().is_ok_and(|rows| rows > self.max_rows) => {
+                    lints.push(lint(Rule::LargeRows, Severity::Warning, format!("rows={} is above {}", value, self.max_rows)));
+                },
+                "start" if value.parse::<u32>().is_ok_and(|start| start > self.max_start) => {
+                    lints.push(lint(Rule::DeepPaging, Severity::Warning, format!("start={} is above {}, page with a cursor", value, self.max_start)));
+                },
+                "cursorMark" => cursor = true,
+                "sort" => sort = Some(value),
+                _ if (key == "facet.limit" || key.ends_with(".facet.limit")) && value.trim() == "-1" => {
+                    lints.push(lint(Rule::UnlimitedFacet, Severity::Warning, format!("{}=-1 returns every facet value", key)));
+                },
+                _ => {}
+            }
+        }
+        if cursor && !sort.is_some_and(|sort| self.sorts_by_unique_key(sort)) {
+            lints.push(lint(Rule::CursorWithoutUniqueKeySort, Severity::Error, format!("cursorMark requires sorting by `{}`", self.unique_key)));
+        }
+        if query == Some("*:*") && !filtered {
+            lints.push(lint(Rule::MatchAllWithoutFilter, Severity::Info, "q=*:* without filter queries matches every document".to_owned()));
+        }
+        lints
+    }
+
+    fn sorts_by_unique_key(&self, sort: &str) -> bool {
+        sort.split(',').any(|clause| clause.split_whitespace().next() == Some(self.unique_key.as_str()))
+    }
+}
+
+fn lint(rule: Rule, severity: Severity, message: String) -> Lint {
+    Lint { rule, severity, message }
+}
+
+/// Collects the term lints, tracking the field of groups.
+struct QueryLints<'a> {
+    linter: &'a Linter,
+    fields: Vec<Option<String>>,
+    lints: Vec<Lint>
+}
+
+impl Visit for QueryLints<'_> {
+    fn visit_term(&mut self, term: &Term) {
+        let field = term.field.as_deref().or(self.fields.iter().rev().find_map(|field| field.as_deref()));
+        let in_field = field.map(|field| format!(" in `{}`", field)).unwrap_or_default();
+        match &term.value {
+            TermValue::Wildcard(pattern) if pattern.starts_with(['*', '?']) => {
+                self.lints.push(lint(Rule::LeadingWildcard, Severity::Warning, format!("leading wildcard `{}`{}", pattern, in_field)));
+            },
+            TermValue::Regex(regex) => {
+                let large = field.is_some_and(|field| self.linter.large_fields.iter().any(|large| large == field));
+                let severity = if large { Severity::Warning } else { Severity::Info };
+                self.lints.push(lint(Rule::Regex, severity, format!("regular expression `/{}/`{}", regex, in_field)));
+            },
+            _ => {}
+        }
+    }
+
+    fn visit_group(&mut self, group: &Group) {
+        self.fields.push(group.field.clone());
+        self.visit_query(&group.query);
+        self.fields.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(lints: Vec<Lint>) -> Vec<(Rule, Severity)> {
+        lints.into_iter().map(|lint| (lint.rule, lint.severity)).collect()
+    }
+
+    #[test]
+    fn lints_leading_wildcards_and_regexes() {
+        let linter = Linter::new().large_fields(&["body"]);
+        let query = Query::parse("title:*ust OR title:ru*t OR body:(/err.*/ OR x) OR name:/a.c/ OR ?ust").unwrap();
+        assert_eq!(rules(linter.lint_query(&query)), vec![
+            (Rule::LeadingWildcard, Severity::Warning),
+            (Rule::Regex, Severity::Warning),
+            (Rule::Regex, Severity::Info),
+            (Rule::LeadingWildcard, Severity::Warning)
+        ]);
+        assert_eq!(linter.lint_query(&query)[1].to_string(), "warning: regular expression `/err.*/` in `body`");
+    }
+
+    #[test]
+    fn lints_paging_and_facets() {
+        let lints = Linter::new().max_rows(50).lint_params([("q", "a"), ("rows", "51"), ("start", "20000"), ("facet.limit", "-1"), ("f.cat.facet.limit", "-1"), ("rows", "50")]);
+        assert_eq!(rules(lints), vec![
+            (Rule::LargeRows, Severity::Warning),
+            (Rule::DeepPaging, Severity::Warning),
+            (Rule::UnlimitedFacet, Severity::Warning),
+            (Rule::UnlimitedFacet, Severity::Warning)
+        ]);
+    }
+
+    #[test]
+    fn lints_cursor_without_unique_key_sort() {
+        let linter = Linter::new().unique_key("sku");
+        assert_eq!(rules(linter.lint_params([("q", "a"), ("cursorMark", "*"), ("sort", "price asc")])), vec![(Rule::CursorWithoutUniqueKeySort, Severity::Error)]);
+        assert_eq!(rules(linter.lint_params([("q", "a"), ("cursorMark", "*")])), vec![(Rule::CursorWithoutUniqueKeySort, Severity::Error)]);
+        assert!(linter.lint_params([("q", "a"), ("cursorMark", "*"), ("sort", "price asc, sku asc")]).is_empty());
+    }
+
+    #[test]
+    fn lints_match_all_without_filters() {
+        assert_eq!(rules(Linter::new().lint_params([("q", "*:*")])), vec![(Rule::MatchAllWithoutFilter, Severity::Info)]);
+        assert!(Linter::new().lint_params([("q", "*:*"), ("fq", "type:book")]).is_empty());
+    }
+
+    #[test]
+    fn lints_filter_queries_and_skips_unparsable_ones() {
+        assert_eq!(rules(Linter::new().lint_params([("fq", "{!cache=false}name:*x"), ("q", "a:(")])), vec![(Rule::LeadingWildcard, Severity::Warning)]);
+    }
+}
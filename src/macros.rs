@@ -0,0 +1,348 @@
+//! The `solr_query!` macro, building a query::Query from Lucene-like syntax checked at compile time.
+//!
+//! ```rust
+//! use rsolr::solr_query;
+//!
+//! let status = "deleted";
+//! let query = solr_query!(title:"foo bar"^2 AND (price:[10 TO *] OR -status:#status));
+//! assert_eq!(query.to_string(), "title:\"foo bar\"^2 AND (price:[10 TO *] OR -status:deleted)");
+//! ```
+//!
+//! Clauses are a value, optionally in a field (`field:value`), with `+` or `-` prefix and
+//! `^boost`, `^=score` or `~distance` suffix. Values are:
+//!
+//! - bare words and literals, i.e. `rust`, `"two words"`, `42`,
+//! - `*`, the field exists, or `*:*` matching all,
+//! - ranges, `[lower TO upper]` inclusive or `{lower TO upper}` exclusive, with `*` for unbounded,
+//! - groups in parentheses,
+//! - `#name` or `#(expression)` interpolating a Rust value through IntoClause: text and numbers are
+//!   escaped, query types are embedded.
+//!
+//! Clauses are joined with `AND`, `OR`, `NOT`, `&&`, `||`, `!` or nothing.
+//!
+//! ```rust
+//! use rsolr::solr_query;
+//! use rsolr::query::{Date, Term};
+//!
+//! let user_input = "c++ (lang)";
+//! let since = Date::now().minus(Date::day(7));
+//! let query = solr_query!(+title:#user_input~1 NOT tag:#(Term::prefix("draft")) created:[#since TO *] || *:*);
+//! assert_eq!(query.to_string(), "+title:\"c++ (lang)\"~1 NOT tag:draft* created:[NOW-7DAYS TO *] OR *:*");
+//! ```
+//!
+//! Malformed queries don't compile.
+//!
+//! ```compile_fail
+//! let query = rsolr::solr_query!(title:rust AND);
+//! ```
+//!
+//! ```compile_fail
+//! let query = rsolr::solr_query!(title:rust OR OR body:rust);
+//! ```
+//!
+//! ```compile_fail
+//! let query = rsolr::solr_query!(price:[10 20]);
+//! ```
+//!
+//! ```compile_fail
+//! let query = rsolr::solr_query!(title:);
+//! ```
+
+use crate::query::{BooleanGroup, BooleanPart, Group, Occur, Query, Term, TermValue};
+
+/// Values interpolated into solr_query! with `#name` or `#(expression)`.
+/// Text and numbers become escaped terms, query types are embedded as they are.
+pub trait IntoClause {
+    fn into_clause(self) -> Query;
+}
+
+macro_rules! text_clause {
+    ($($type:ty),*) => {
+        $(
+            impl IntoClause for $type {
+                fn into_clause(self) -> Query {
+                    Query::Term(Term::from_str(&self.to_string()))
+                }
+            }
+        )*
+    };
+}
+
+text_clause!(&str, String, &String, char, bool, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+impl IntoClause for Term {
+    fn into_clause(self) -> Query {
+        Query::Term(self)
+    }
+}
+
+impl IntoClause for Group {
+    fn into_clause(self) -> Query {
+        Query::Group(self)
+    }
+}
+
+impl IntoClause for BooleanGroup {
+    fn into_clause(self) -> Query {
+        Query::BooleanGroup(self)
+    }
+}
+
+impl IntoClause for Query {
+    fn into_clause(self) -> Query {
+        self
+    }
+}
+
+#[doc(hidden)]
+pub fn in_field(query: Query, field: Option<&str>) -> Query {
+    match (field, query) {
+        (None, query) => query,
+        (Some(field), Query::Term(term)) if !matches!(term.value, TermValue::Raw(_)) => Query::Term(term.in_field(field)),
+        (Some(field), Query::Group(group)) => Query::Group(group.in_field(field)),
+        (Some(field), query) => Query::Group(Group::new(query).in_field(field))
+    }
+}
+
+#[doc(hidden)]
+pub fn occur(query: Query, occur: Option<Occur>) -> Query {
+    let mut query = match (occur, query) {
+        (None, query) => return query,
+        (Some(_), query @ (Query::Term(_) | Query::Group(_) | Query::BooleanGroup(_))) => query,
+        (Some(_), query) => Query::Group(Group::new(query))
+    };
+    match &mut query {
+        Query::Term(term) => term.modifiers.occur = occur,
+        Query::Group(group) => group.modifiers.occur = occur,
+        Query::BooleanGroup(group) => group.modifiers.occur = occur,
+        _ => ()
+    }
+    query
+}
+
+/// Slop of phrases, edit distance of text, other values are left as they are.
+#[doc(hidden)]
+pub fn tilde(query: Query, value: u32) -> Query {
+    match query {
+        Query::Term(term) => Query::Term(match term.value {
+            TermValue::Text(ref text) => Term { value: Term::fuzzy(text, value.min(u8::MAX.into()) as u8).value, ..term },
+            _ => term.slop(value)
+        }),
+        query => query
+    }
+}
+
+#[doc(hidden)]
+pub fn parts() -> Vec<BooleanPart> {
+    Vec::new()
+}
+
+#[doc(hidden)]
+pub fn boolean(mut parts: Vec<BooleanPart>) -> Query {
+    match (parts.len(), parts.pop()) {
+        (1, Some(BooleanPart::Clause(query))) => query,
+        (_, Some(part)) => {
+            parts.push(part);
+            Query::Boolean(parts)
+        },
+        (_, None) => Query::Boolean(parts)
+    }
+}
+
+/// Builds a query::Query from Lucene-like syntax, see the macros module.
+#[macro_export]
+macro_rules! solr_query {
+    ($($tokens:tt)+) => {{
+        let mut parts = $crate::macros::parts();
+        $crate::__solr_query!(@clause parts; $($tokens)+);
+        $crate::macros::boolean(parts)
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __solr_query {
+    // A clause is expected, optionally negated.
+    (@clause $parts:ident;) => { compile_error!("expected a clause") };
+    (@clause $parts:ident; NOT NOT $($rest:tt)*) => { compile_error!("`NOT` is not followed by a clause") };
+    (@clause $parts:ident; NOT $($rest:tt)*) => {
+        $parts.push($crate::query::BooleanPart::Operator($crate::query::Operator::Not));
+        $crate::__solr_query!(@occur $parts; $($rest)*);
+    };
+    (@clause $parts:ident; ! $($rest:tt)*) => { $crate::__solr_query!(@clause $parts; NOT $($rest)*); };
+    (@clause $parts:ident; AND $($rest:tt)*) => { compile_error!("`AND` is not preceded by a clause") };
+    (@clause $parts:ident; && $($rest:tt)*) => { compile_error!("`&&` is not preceded by a clause") };
+    (@clause $parts:ident; OR $($rest:tt)*) => { compile_error!("`OR` is not preceded by a clause") };
+    (@clause $parts:ident; || $($rest:tt)*) => { compile_error!("`||` is not preceded by a clause") };
+    (@clause $parts:ident; $($rest:tt)+) => { $crate::__solr_query!(@occur $parts; $($rest)+); };
+
+    // The `+` or `-` prefix.
+    (@occur $parts:ident;) => { compile_error!("expected a clause") };
+    (@occur $parts:ident; + $($rest:tt)+) => {
+        $crate::__solr_query!(@field $parts (::std::option::Option::Some($crate::query::Occur::Required)); $($rest)+);
+    };
+    (@occur $parts:ident; - $($rest:tt)+) => {
+        $crate::__solr_query!(@field $parts (::std::option::Option::Some($crate::query::Occur::Prohibited)); $($rest)+);
+    };
+    (@occur $parts:ident; $($rest:tt)+) => { $crate::__solr_query!(@field $parts (::std::option::Option::None); $($rest)+); };
+
+    // The field of the clause.
+    (@field $parts:ident ($occur:expr); * : * $($rest:tt)*) => {
+        $crate::__solr_query!(@modifiers $parts ($occur) ($crate::query::Query::raw("*:*")); $($rest)*);
+    };
+    (@field $parts:ident ($occur:expr); $field:ident : $($rest:tt)*) => {
+        $crate::__solr_query!(@value $parts ($occur) (::std::option::Option::Some(stringify!($field))); $($rest)*);
+    };
+    (@field $parts:ident ($occur:expr); $($rest:tt)*) => {
+        $crate::__solr_query!(@value $parts ($occur) (::std::option::Option::None); $($rest)*);
+    };
+
+    // The value of the clause.
+    (@value $parts:ident ($occur:expr) ($field:expr);) => { compile_error!("expected a value") };
+    (@value $parts:ident ($occur:expr) ($field:expr); * $($rest:tt)*) => {
+        $crate::__solr_query!(@modifiers $parts ($occur) (
+            match $field {
+                ::std::option::Option::Some(field) => $crate::query::Query::Term($crate::query::Term::exists(field)),
+                ::std::option::Option::None => $crate::query::Query::Term($crate::query::Term::wildcard("*"))
+            }
+        ); $($rest)*);
+    };
+    (@value $parts:ident ($occur:expr) ($field:expr); # $value:ident $($rest:tt)*) => {
+        $crate::__solr_query!(@modifiers $parts ($occur) (
+            $crate::macros::in_field($crate::macros::IntoClause::into_clause($value), $field)
+        ); $($rest)*);
+    };
+    (@value $parts:ident ($occur:expr) ($field:expr); # ($value:expr) $($rest:tt)*) => {
+        $crate::__solr_query!(@modifiers $parts ($occur) (
+            $crate::macros::in_field($crate::macros::IntoClause::into_clause($value), $field)
+        ); $($rest)*);
+    };
+    (@value $parts:ident ($occur:expr) ($field:expr); [$($range:tt)+] $($rest:tt)*) => {
+        $crate::__solr_query!(@range $parts ($occur) ($field) inclusive [$($range)+]; $($rest)*);
+    };
+    (@value $parts:ident ($occur:expr) ($field:expr); {$($range:tt)+} $($rest:tt)*) => {
+        $crate::__solr_query!(@range $parts ($occur) ($field) exclusive [$($range)+]; $($rest)*);
+    };
+    (@value $parts:ident ($occur:expr) ($field:expr); ($($group:tt)+) $($rest:tt)*) => {
+        $crate::__solr_query!(@modifiers $parts ($occur) (
+            $crate::macros::in_field($crate::query::Query::Group($crate::query::Group::new($crate::solr_query!($($group)+))), $field)
+        ); $($rest)*);
+    };
+    (@value $parts:ident ($occur:expr) ($field:expr); $value:literal $($rest:tt)*) => {
+        $crate::__solr_query!(@modifiers $parts ($occur) (
+            $crate::macros::in_field($crate::query::Query::Term($crate::query::Term::from_str(&$value.to_string())), $field)
+        ); $($rest)*);
+    };
+    (@value $parts:ident ($occur:expr) ($field:expr); $value:ident $($rest:tt)*) => {
+        $crate::__solr_query!(@modifiers $parts ($occur) (
+            $crate::macros::in_field($crate::query::Query::Term($crate::query::Term::from_str(stringify!($value))), $field)
+        ); $($rest)*);
+    };
+
+    // Range bounds, a literal, `*`, `#name` or `#(expression)`.
+    (@range $parts:ident ($occur:expr) ($field:expr) $kind:ident [# $lower:tt TO $($upper:tt)+]; $($rest:tt)*) => {
+        $crate::__solr_query!(@bounds $parts ($occur) ($field) $kind [# $lower] [$($upper)+]; $($rest)*);
+    };
+    (@range $parts:ident ($occur:expr) ($field:expr) $kind:ident [$lower:tt TO $($upper:tt)+]; $($rest:tt)*) => {
+        $crate::__solr_query!(@bounds $parts ($occur) ($field) $kind [$lower] [$($upper)+]; $($rest)*);
+    };
+    (@bounds $parts:ident ($occur:expr) ($field:expr) $kind:ident [$($lower:tt)+] [$($upper:tt)+]; $($rest:tt)*) => {
+        $crate::__solr_query!(@modifiers $parts ($occur) (
+            $crate::macros::in_field($crate::query::Query::Term($crate::query::Term::range($crate::query::Range::new(
+                $crate::__solr_query!(@bound $kind $($lower)+),
+                $crate::__solr_query!(@bound $kind $($upper)+)
+            ))), $field)
+        ); $($rest)*);
+    };
+    (@bound $kind:ident *) => { $crate::query::Bound::Unbounded };
+    (@bound $kind:ident $value:literal) => { $crate::query::Bound::$kind($value) };
+    (@bound $kind:ident # $value:ident) => { $crate::query::Bound::$kind(&$value) };
+    (@bound $kind:ident # ($value:expr)) => { $crate::query::Bound::$kind($value) };
+
+    // Suffixes, then the clause is complete.
+    (@modifiers $parts:ident ($occur:expr) ($query:expr); ^= $score:literal $($rest:tt)*) => {
+        $crate::__solr_query!(@modifiers $parts ($occur) ($query.constant_score($score as f32)); $($rest)*);
+    };
+    (@modifiers $parts:ident ($occur:expr) ($query:expr); ^ $boost:literal $($rest:tt)*) => {
+        $crate::__solr_query!(@modifiers $parts ($occur) ($query.boost($boost as f32)); $($rest)*);
+    };
+    (@modifiers $parts:ident ($occur:expr) ($query:expr); ~ $distance:literal $($rest:tt)*) => {
+        $crate::__solr_query!(@modifiers $parts ($occur) ($crate::macros::tilde($query, $distance)); $($rest)*);
+    };
+    (@modifiers $parts:ident ($occur:expr) ($query:expr); ~ $($rest:tt)*) => {
+        $crate::__solr_query!(@modifiers $parts ($occur) ($crate::macros::tilde($query, $crate::query::MAX_EDIT_DISTANCE.into())); $($rest)*);
+    };
+    (@modifiers $parts:ident ($occur:expr) ($query:expr); $($rest:tt)*) => {
+        $parts.push($crate::query::BooleanPart::Clause($crate::macros::occur($query, $occur)));
+        $crate::__solr_query!(@operator $parts; $($rest)*);
+    };
+
+    // After a clause: the end, an operator or the next clause.
+    (@operator $parts:ident;) => {};
+    (@operator $parts:ident; AND $($rest:tt)*) => { $crate::__solr_query!(@operator $parts And; $($rest)*); };
+    (@operator $parts:ident; && $($rest:tt)*) => { $crate::__solr_query!(@operator $parts And; $($rest)*); };
+    (@operator $parts:ident; OR $($rest:tt)*) => { $crate::__solr_query!(@operator $parts Or; $($rest)*); };
+    (@operator $parts:ident; || $($rest:tt)*) => { $crate::__solr_query!(@operator $parts Or; $($rest)*); };
+    (@operator $parts:ident; $($rest:tt)+) => { $crate::__solr_query!(@clause $parts; $($rest)+); };
+    (@operator $parts:ident $operator:ident;) => { compile_error!("expected a clause after the operator") };
+    (@operator $parts:ident $operator:ident; $($rest:tt)+) => {
+        $parts.push($crate::query::BooleanPart::Operator($crate::query::Operator::$operator));
+        $crate::__solr_query!(@clause $parts; $($rest)+);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::{BooleanGroup, Date, Group, Query, Range, Term};
+
+    #[test]
+    fn builds_terms_phrases_and_fields() {
+        assert_eq!(crate::solr_query!(rust), Query::from_term(Term::from_str("rust")));
+        assert_eq!(crate::solr_query!(title:"foo bar"), Query::from_term(Term::phrase("foo bar").in_field("title")));
+        assert_eq!(crate::solr_query!(price:42).to_string(), "price:42");
+        assert_eq!(crate::solr_query!(*:*), Query::raw("*:*"));
+        assert_eq!(crate::solr_query!(title:*), Query::from_term(Term::exists("title")));
+    }
+
+    #[test]
+    fn builds_boolean_parts_like_the_builder() {
+        let built = Query::from_term(Term::from_str("a").in_field("title").required())
+            .and()
+            .subquery(Query::from_term(Term::range(10..=20).in_field("price")).or().term(Term::from_str("deleted").in_field("status").prohibit()))
+            .not()
+            .term(Term::from_str("b"));
+        assert_eq!(crate::solr_query!(+title:a AND (price:[10 TO 20] OR -status:deleted) NOT b), built);
+    }
+
+    #[test]
+    fn applies_modifiers() {
+        assert_eq!(crate::solr_query!(title:"a b"~2^3).to_string(), "title:\"a b\"~2^3");
+        assert_eq!(crate::solr_query!(rust~ OR solr~1).to_string(), "rust~2 OR solr~1");
+        assert_eq!(crate::solr_query!(-(a OR b)^=1.5 c).to_string(), "-(a OR b)^=1.5 c");
+    }
+
+    #[test]
+    fn interpolates_escaped_values() {
+        let input = "a:b OR c";
+        let year = 2024;
+        assert_eq!(crate::solr_query!(title:#input AND year:#year).to_string(), "title:\"a:b OR c\" AND year:2024");
+        assert_eq!(crate::solr_query!(#(input.replace(' ', ""))).to_string(), "a\\:bORc");
+    }
+
+    #[test]
+    fn interpolates_query_types() {
+        let group = BooleanGroup::new().must(Term::from_str("x")).must(Term::from_str("y"));
+        let query = crate::solr_query!(a OR #(group.clone()) OR tags:#(Query::raw("p q")));
+        let expected = Query::from_term(Term::from_str("a")).or().subquery(group.into()).or().group(Group::new(Query::raw("p q")).in_field("tags"));
+        assert_eq!(query.to_string(), "a OR (+x +y) OR tags:(p q)");
+        assert_eq!(query.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn builds_ranges_with_interpolated_bounds() {
+        let since = Date::now().minus(Date::day(1));
+        assert_eq!(crate::solr_query!(created:[#since TO *]), Query::from_term(Term::range(Range::at_least(since.clone())).in_field("created")));
+        assert_eq!(crate::solr_query!(price:{#(1.5) TO 10}).to_string(), "price:{1.5 TO 10}");
+        assert_eq!(crate::solr_query!(name:[* TO "m"]).to_string(), "name:[* TO m]");
+    }
+}
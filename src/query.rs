@@ -1,163 +1,1286 @@
 //! You can build Solr queries with this module.
 //! For easy integration queries should be rendered to str at the end, if you want to use in the client.
-//! Currently it's rather cumbersome, on it.
+//! The solr_query! macro builds the same trees from Lucene-like syntax, see the macros module.
 //!
 //! ```rust
 //!
-//! use rsolr::query::{Query, Range, Term, Stringable, Date};
+//! use rsolr::query::{Query, Range, Term, Date};
 //! fn query() -> Query {
 //!     Query::from_term(
-//!         Term::from_str("simple").boost(2.3).tilde(3).required()
+//!         Term::fuzzy("simple", 2).boost(2.3).required()
 //!         )
 //!         .term(Term::from_str("next to the first term").in_field("with_field_specification"))
 //!         .and()
 //!         .subquery(
 //!             Query::from_term(
-//!                 Term::from_str(&Range::inclusive("1", "1000").as_str()).in_field("popularity")
+//!                 Term::range(1..=1000).in_field("popularity")
 //!                 )
 //!                 .or()
-//!                 .term(Term::from_str(&Range::inclusive(&Date::new("NOW").minus(&Date::month(2)).as_str(), "NOW").as_str()).in_field("created"))
+//!                 .term(Term::range(Range::at_least(Date::now().minus(Date::month(2)))).in_field("created"))
 //!         )
 //! }
 //! ```
 //!
+//! Terms and phrases escape their value, so user input cannot change the query. Trusted query
+//! syntax goes through Term::raw or Query::raw.
 //!
+//! ```rust
+//! use rsolr::query::{escape_query_chars, Query, Term};
+//!
+//! assert_eq!(Term::from_str("c++ (lang)").in_field("title").to_string(), "title:\"c++ (lang)\"");
+//! assert_eq!(Term::from_str("a:b&&c").to_string(), "a\\:b\\&\\&c");
+//! assert_eq!(Query::from_str("*:*").to_string(), "\\*\\:\\*");
+//! assert_eq!(Query::raw("*:*").to_string(), "*:*");
+//! assert_eq!(escape_query_chars("1/2"), "1\\/2");
+//! ```
+//!
+//! Ranges take typed bounds, Rust ranges convert to them. With the `chrono` or `time` feature
+//! their timestamps are bounds as well, formatted as UTC ISO-8601.
+//!
+//! ```rust
+//! use rsolr::query::{Bound, Range, Term};
+//!
+//! assert_eq!(Term::range(10..20).in_field("price").to_string(), "price:[10 TO 20}");
+//! assert_eq!(Term::range(Range::at_least(4.5)).in_field("rating").to_string(), "rating:[4.5 TO *]");
+//! assert_eq!(Range::new(Bound::exclusive("a"), Bound::Unbounded).to_string(), "{a TO *]");
+//! ```
+//!
+//! Queries are trees, they can be cloned, compared and inspected, and they render through Display.
+//!
+//! ```rust
+//! use rsolr::query::{Query, Term, TermValue};
+//!
+//! let query = Query::from_term(Term::from_str("rust").in_field("title")).or().term(Term::from_str("solr"));
+//! assert_eq!(query.to_string(), "title:rust OR solr");
+//!
+//! if let Query::Boolean(parts) = &query {
+//!     assert_eq!(parts.len(), 3);
+//! }
+//! assert_eq!(Term::from_str("two words").value, TermValue::Phrase { phrase: "two words".to_owned(), slop: None });
+//! ```
+//!
+//! Query strings, i.e. saved searches, parse into the same tree to inspect, rewrite and render them again.
+//!
+//! ```rust
+//! use rsolr::query::{Query, Term};
+//!
+//! let query = Query::parse("title:(rust OR solr)^2 AND -draft").expect("invalid query");
+//! assert_eq!(query.and().term(Term::from_str("book").in_field("type")).to_string(), "title:(rust OR solr)^2 AND -draft AND type:book");
+//! assert!(Query::parse("title:(rust OR").is_err());
+//! ```
+//!
+//! Boolean groups hold must, should, must not and filter clauses. They render with explicit
+//! `+`, `-` and `OR`, so the query means the same whatever the default `q.op` is.
+//!
+//! ```rust
+//! use rsolr::query::{BooleanGroup, MinimumShouldMatch, Query, Term};
+//!
+//! let group = BooleanGroup::new()
+//!     .must(Term::from_str("rust").in_field("title"))
+//!     .filter(Term::from_str("book").in_field("type"))
+//!     .must_not(Term::from_str("draft").in_field("status"));
+//! assert_eq!(Query::from(group).to_string(), "+title:rust +filter(type:book) -status:draft");
+//!
+//! let negative = BooleanGroup::new().must_not(Term::from_str("draft").in_field("status"));
+//! let query = Query::from_str("a").and().subquery(Query::from(negative).constant_score(1.0));
+//! assert_eq!(query.to_string(), "a AND ((*:* -status:draft)^=1)");
+//!
+//! let at_least_two = BooleanGroup::new()
+//!     .should(Term::from_str("a"))
+//!     .should(Term::from_str("b"))
+//!     .should(Term::from_str("c"))
+//!     .minimum_should_match(MinimumShouldMatch::count(2));
+//! assert_eq!(Query::from(at_least_two).to_string(), "{!bool should=a should=b should=c mm=2}");
+//! ```
+//!
+//! Local params switch the query parser or set its options. They wrap queries, filters, facets
+//! or any other parameter value.
+//!
+//! ```rust
+//! use rsolr::Client;
+//! use rsolr::query::{LocalParams, Query, Stringable};
+//!
+//! fn local_params() {
+//!     let parent = LocalParams::new("parent").param("which", "type:parent");
+//!     Client::new("http://solr:8983", "collection")
+//!         .select(Query::raw("color:red").local_params(parent))
+//!         .add_query_param("qq", "title:rust")
+//!         .filter_query(LocalParams::new("lucene").param("q.op", "AND").deref("v", "qq").as_str())
+//!         .facet_field(&LocalParams::empty().param("ex", "type").wrap("type"))
+//!         .run().expect("request failed.");
+//! }
+//! ```
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use regex::Regex;
+use crate::error::RSolrError;
+
+mod minimum_should_match;
+mod parser;
+pub mod rewrite;
+pub mod sanitize;
+pub mod template;
+
+pub use minimum_should_match::MinimumShouldMatch;
 
 pub trait Stringable {
     fn as_str(&self) -> String;
     fn is_query(&self) -> bool;
 }
 
-pub struct And {}
-impl Stringable for And {
-    fn as_str(&self) -> String {
-        "AND".to_owned()
+/// Boolean operator between the clauses of a boolean query.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    And,
+    Or,
+    Not
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operator::And => write!(f, "AND"),
+            Operator::Or => write!(f, "OR"),
+            Operator::Not => write!(f, "NOT")
+        }
+    }
+}
+
+/// The `+` and `-` prefix of a clause.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Occur {
+    Required,
+    Prohibited
+}
+
+impl Display for Occur {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Occur::Required => write!(f, "+"),
+            Occur::Prohibited => write!(f, "-")
+        }
+    }
+}
+
+/// Modifiers of a clause, rendered in a fixed order whatever the order they were set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Modifiers {
+    pub occur: Option<Occur>,
+    pub boost: Option<f32>,
+    /// Constant score `^=`, replaces the boost.
+    pub constant_score: Option<f32>
+}
+
+impl Modifiers {
+    fn prefix(&self) -> String {
+        self.occur.map(|occur| occur.to_string()).unwrap_or_default()
+    }
+
+    fn suffix(&self) -> String {
+        let mut suffix = String::new();
+        if let Some(boost) = self.boost {
+            suffix += &format!("^{}", boost);
+        }
+        if let Some(score) = self.constant_score {
+            suffix += &format!("^={}", score);
+        }
+        suffix
+    }
+
+    fn is_empty(&self) -> bool {
+        self == &Modifiers::default()
+    }
+
+    fn set_boost(&mut self, value: f32) {
+        self.boost = Some(value);
+        self.constant_score = None;
+    }
+
+    fn set_constant_score(&mut self, value: f32) {
+        self.constant_score = Some(value);
+        self.boost = None;
+    }
+}
+
+/// A part of a boolean query: a clause or an operator between clauses.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BooleanPart {
+    Clause(Query),
+    Operator(Operator)
+}
+
+/// Parenthesized subquery, optionally in a field.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Group {
+    pub field: Option<String>,
+    pub query: Box<Query>,
+    pub modifiers: Modifiers
+}
+
+impl Group {
+
+    pub fn new(query: Query) -> Self {
+        Group { field: None, query: Box::new(query), modifiers: Modifiers::default() }
+    }
+
+    pub fn in_field(mut self, field: &str) -> Self {
+        self.field = Some(field.to_owned());
+        self
+    }
+
+    pub fn boost(mut self, value: f32) -> Self {
+        self.modifiers.set_boost(value);
+        self
+    }
+
+    /// Every match scores the value, rendered as `^=`.
+    pub fn constant_score(mut self, value: f32) -> Self {
+        self.modifiers.set_constant_score(value);
+        self
+    }
+
+    pub fn required(mut self) -> Self {
+        self.modifiers.occur = Some(Occur::Required);
+        self
+    }
+
+    pub fn prohibit(mut self) -> Self {
+        self.modifiers.occur = Some(Occur::Prohibited);
+        self
+    }
+}
+
+impl Display for Group {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.modifiers.prefix())?;
+        if let Some(field) = &self.field {
+            write!(f, "{}:", escape_query_chars(field))?;
+        }
+        write!(f, "({}){}", self.query.render(true), self.modifiers.suffix())
+    }
+}
+
+/// Boolean group of clause lists. It renders with `+`, `-`, `OR` and `filter()`, with should clauses
+/// next to required ones or minimum-should-match it switches to the `bool` query parser.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BooleanGroup {
+    pub must: Vec<Query>,
+    pub should: Vec<Query>,
+    pub must_not: Vec<Query>,
+    /// Required clauses not affecting the score.
+    pub filter: Vec<Query>,
+    pub minimum_should_match: Option<MinimumShouldMatch>,
+    pub modifiers: Modifiers
+}
+
+impl BooleanGroup {
+
+    pub fn new() -> Self {
+        BooleanGroup::default()
+    }
+
+    pub fn must<Q: Into<Query>>(mut self, query: Q) -> Self {
+        self.must.push(query.into());
+        self
+    }
+
+    pub fn should<Q: Into<Query>>(mut self, query: Q) -> Self {
+        self.should.push(query.into());
+        self
+    }
+
+    pub fn must_not<Q: Into<Query>>(mut self, query: Q) -> Self {
+        self.must_not.push(query.into());
+        self
+    }
+
+    pub fn filter<Q: Into<Query>>(mut self, query: Q) -> Self {
+        self.filter.push(query.into());
+        self
+    }
+
+    /// How many should clauses must match, rendered through the `bool` query parser.
+    pub fn minimum_should_match(mut self, mm: MinimumShouldMatch) -> Self {
+        self.minimum_should_match = Some(mm);
+        self
+    }
+
+    pub fn boost(mut self, value: f32) -> Self {
+        self.modifiers.set_boost(value);
+        self
+    }
+
+    /// Every match scores the value, rendered as `^=`.
+    pub fn constant_score(mut self, value: f32) -> Self {
+        self.modifiers.set_constant_score(value);
+        self
+    }
+
+    pub fn required(mut self) -> Self {
+        self.modifiers.occur = Some(Occur::Required);
+        self
+    }
+
+    pub fn prohibit(mut self) -> Self {
+        self.modifiers.occur = Some(Occur::Prohibited);
+        self
+    }
+
+    /// With `q.op=AND` a lone should clause next to required ones would become required.
+    fn uses_bool_parser(&self) -> bool {
+        let has_required = !self.must.is_empty() || !self.filter.is_empty();
+        self.minimum_should_match.is_some() || (!self.should.is_empty() && has_required)
+    }
+
+    fn is_pure_negative(&self) -> bool {
+        self.must.is_empty() && self.should.is_empty() && self.filter.is_empty() && !self.must_not.is_empty()
+    }
+
+    /// Whether it renders without parentheses of its own as several clauses or as a clause with an
+    /// occurrence prefix, which would apply to the enclosing query.
+    fn is_compound(&self) -> bool {
+        let clauses = self.must.len() + self.should.len() + self.must_not.len() + self.filter.len();
+        self.modifiers.is_empty() && !self.uses_bool_parser() && (clauses > 1 || (clauses == 1 && self.should.is_empty()))
+    }
+
+    /// Nested pure negative groups match nothing in Lucene, they get a `*:*` clause.
+    fn render(&self, nested: bool) -> String {
+        let body = match self.uses_bool_parser() {
+            true => {
+                let mut local_params = LocalParams::new("bool");
+                for (key, clauses) in [("must", &self.must), ("should", &self.should), ("must_not", &self.must_not), ("filter", &self.filter)] {
+                    for clause in clauses {
+                        local_params = local_params.param(key, &clause.render(false));
+                    }
+                }
+                if let Some(mm) = &self.minimum_should_match {
+                    local_params = local_params.param("mm", &mm.to_string());
+                }
+                local_params.as_str()
+            },
+            false => {
+                let mut clauses = vec![];
+                if nested && self.is_pure_negative() {
+                    clauses.push("*:*".to_owned());
+                }
+                if !self.should.is_empty() {
+                    let should: Vec<String> = self.should.iter().map(|clause| clause.without_occur().render_clause()).collect();
+                    clauses.push(should.join(" OR "));
+                }
+                clauses.extend(self.must.iter().map(|clause| format!("+{}", clause.without_occur().render_clause())));
+                clauses.extend(self.filter.iter().map(|clause| format!("+filter({})", clause.render(true))));
+                clauses.extend(self.must_not.iter().map(|clause| format!("-{}", clause.without_occur().render_clause())));
+                clauses.join(" ")
+            }
+        };
+        match self.modifiers.is_empty() {
+            true => body,
+            false => format!("{}({}){}", self.modifiers.prefix(), body, self.modifiers.suffix())
+        }
+    }
+}
+
+/// The query tree. Builder methods extend it, rendering it happens through Display.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Query {
+    Term(Term),
+    Boolean(Vec<BooleanPart>),
+    Group(Group),
+    BooleanGroup(BooleanGroup),
+    LocalParams(LocalParams, Box<Query>)
+}
+
+impl Query {
+
+    /// Query of a single term from user input, escaped like Term::from_str.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(str: &str) -> Self {
+        Query::Term(Term::from_str(str))
+    }
+
+    /// Query from trusted query syntax rendered without escaping, never use it with user input.
+    pub fn raw(raw: &str) -> Self {
+        Query::Term(Term::raw(raw))
+    }
+
+    /// Parses standard query parser syntax, the error tells the character position of invalid syntax.
+    pub fn parse(query: &str) -> Result<Self, RSolrError> {
+        parser::parse(query)
+    }
+
+    pub fn from_term(term: Term) -> Self {
+        Query::Term(term)
+    }
+
+    pub fn term(self, term: Term) -> Self {
+        self.push(BooleanPart::Clause(Query::Term(term)))
+    }
+
+    pub fn and(self) -> Self {
+        self.push(BooleanPart::Operator(Operator::And))
+    }
+
+    pub fn or(self) -> Self {
+        self.push(BooleanPart::Operator(Operator::Or))
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        self.push(BooleanPart::Operator(Operator::Not))
+    }
+
+    pub fn subquery(self, query: Query) -> Self {
+        self.push(BooleanPart::Clause(Query::Group(Group::new(query))))
+    }
+
+    /// Adds the group as is, i.e. with field or boost.
+    pub fn group(self, group: Group) -> Self {
+        self.push(BooleanPart::Clause(Query::Group(group)))
+    }
+
+    /// Rewrites the query, none if the folder dropped all of it.
+    pub fn fold<F: rewrite::Fold>(self, folder: &mut F) -> Option<Self> {
+        folder.fold_query(self)
+    }
+
+    pub fn visit<V: rewrite::Visit>(&self, visitor: &mut V) {
+        visitor.visit_query(self)
+    }
+
+    /// Lints of the terms with the default linter, see lint::Linter.
+    pub fn lint(&self) -> Vec<crate::lint::Lint> {
+        crate::lint::Linter::default().lint_query(self)
+    }
+
+    /// Prefixes the query with local params.
+    pub fn local_params(self, local_params: LocalParams) -> Self {
+        Query::LocalParams(local_params, Box::new(self))
+    }
+
+    /// Boosts the whole query, wrapping it into a group if it cannot take a boost itself.
+    pub fn boost(self, value: f32) -> Self {
+        match self {
+            Query::Term(term) => Query::Term(term.boost(value)),
+            Query::Group(group) => Query::Group(group.boost(value)),
+            Query::BooleanGroup(group) => Query::BooleanGroup(group.boost(value)),
+            query => Query::Group(Group::new(query).boost(value))
+        }
+    }
+
+    /// Every match of the whole query scores the value, wrapping it into a group if needed.
+    pub fn constant_score(self, value: f32) -> Self {
+        match self {
+            Query::Term(term) => Query::Term(term.constant_score(value)),
+            Query::Group(group) => Query::Group(group.constant_score(value)),
+            Query::BooleanGroup(group) => Query::BooleanGroup(group.constant_score(value)),
+            query => Query::Group(Group::new(query).constant_score(value))
+        }
+    }
+
+    fn push(self, part: BooleanPart) -> Self {
+        match self {
+            Query::Boolean(mut parts) => {
+                parts.push(part);
+                Query::Boolean(parts)
+            },
+            Query::LocalParams(local_params, query) => Query::LocalParams(local_params, Box::new(query.push(part))),
+            query => Query::Boolean(vec![BooleanPart::Clause(query), part])
+        }
+    }
+
+    /// Nested local params cannot prefix the rest of the query, they take their query in `v`.
+    fn render(&self, nested: bool) -> String {
+        match self {
+            Query::Term(term) => term.to_string(),
+            Query::Group(group) => group.to_string(),
+            Query::BooleanGroup(group) => group.render(nested),
+            Query::Boolean(parts) => {
+                let parts: Vec<String> = parts.iter().map(|part| match part {
+                    BooleanPart::Clause(query) => query.render_clause(),
+                    BooleanPart::Operator(operator) => operator.to_string()
+                }).collect();
+                parts.join(" ")
+            },
+            Query::LocalParams(local_params, query) => match (nested, query.render(false)) {
+                (true, rendered) if rendered.is_empty() => local_params.as_str(),
+                (true, rendered) => local_params.clone().value(&rendered).as_str(),
+                (false, rendered) => local_params.wrap(&rendered)
+            }
+        }
+    }
+}
+
+impl Query {
+    /// Renders as a clause of another query, parenthesized if it consists of several clauses.
+    fn render_clause(&self) -> String {
+        match self {
+            Query::Boolean(_) => format!("({})", self.render(true)),
+            Query::BooleanGroup(group) if group.is_compound() => format!("({})", self.render(true)),
+            query => query.render(true)
+        }
+    }
+
+    /// The occurrence comes from the clause list of a boolean group.
+    fn without_occur(&self) -> Query {
+        let mut query = self.clone();
+        match &mut query {
+            Query::Term(term) => term.modifiers.occur = None,
+            Query::Group(group) => group.modifiers.occur = None,
+            Query::BooleanGroup(group) => group.modifiers.occur = None,
+            _ => ()
+        }
+        query
+    }
+}
+
+impl Display for Query {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
+impl Stringable for Query {
+    fn as_str(&self) -> String {
+        self.to_string()
+    }
+
+    fn is_query(&self) -> bool {
+        true
+    }
+}
+
+impl From<Term> for Query {
+    fn from(term: Term) -> Self {
+        Query::Term(term)
+    }
+}
+
+impl From<Range> for Term {
+    fn from(range: Range) -> Self {
+        Term::range(range)
+    }
+}
+
+impl From<Group> for Query {
+    fn from(group: Group) -> Self {
+        Query::Group(group)
+    }
+}
+
+impl From<BooleanGroup> for Query {
+    fn from(group: BooleanGroup) -> Self {
+        Query::BooleanGroup(group)
+    }
+}
+
+/// Maximum edit distance of fuzzy terms supported by Solr.
+pub const MAX_EDIT_DISTANCE: u8 = 2;
+
+/// The value of a term clause. Values hold the unescaped input, except raw which is rendered as is.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TermValue {
+    Text(String),
+    Phrase { phrase: String, slop: Option<u32> },
+    Range(Range),
+    /// Pattern where `*` and `?` are wildcards, anything else is escaped.
+    Wildcard(String),
+    Prefix(String),
+    Fuzzy { text: String, distance: u8 },
+    /// Regular expression without the enclosing slashes.
+    Regex(String),
+    /// Any value, the field exists.
+    Exists,
+    Raw(String)
+}
+
+impl Display for TermValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TermValue::Text(text) if text.is_empty() || ["AND", "OR", "NOT"].contains(&text.as_str()) => write!(f, "\"{}\"", text),
+            TermValue::Text(text) => write!(f, "{}", escape_query_chars(text)),
+            TermValue::Phrase { phrase, slop: Some(slop) } => write!(f, "\"{}\"~{}", escape_phrase(phrase), slop),
+            TermValue::Phrase { phrase, slop: None } => write!(f, "\"{}\"", escape_phrase(phrase)),
+            TermValue::Range(range) => write!(f, "{}", range),
+            TermValue::Wildcard(pattern) => {
+                let parts: Vec<String> = pattern.split_inclusive(['*', '?']).map(|part| match part.strip_suffix(['*', '?']) {
+                    Some(literal) => format!("{}{}", escape_query_chars(literal), &part[literal.len()..]),
+                    None => escape_query_chars(part)
+                }).collect();
+                write!(f, "{}", parts.concat())
+            },
+            TermValue::Prefix(prefix) => write!(f, "{}*", escape_query_chars(prefix)),
+            TermValue::Fuzzy { text, distance } => write!(f, "{}~{}", escape_query_chars(text), distance),
+            TermValue::Regex(regex) => write!(f, "/{}/", escape_regex_delimiter(regex)),
+            TermValue::Exists => write!(f, "*"),
+            TermValue::Raw(raw) => write!(f, "{}", raw)
+        }
+    }
+}
+
+/// A single clause: a value, optionally in a field, with its modifiers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Term {
+    pub field: Option<String>,
+    pub value: TermValue,
+    pub modifiers: Modifiers
+}
+
+impl Term {
+
+    /// Term from user input, special characters are escaped, with whitespace it becomes a phrase.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(term_str: &str) -> Self {
+        match term_str.contains(char::is_whitespace) {
+            true => Term::phrase(term_str),
+            false => Term::new(TermValue::Text(term_str.to_owned()))
+        }
+    }
+
+    /// Phrase from user input, quotes and backslashes are escaped.
+    pub fn phrase(phrase: &str) -> Self {
+        Term::new(TermValue::Phrase { phrase: phrase.to_owned(), slop: None })
+    }
+
+    /// Wildcard term, `*` matches any characters, `?` a single one, i.e. `te?t*`.
+    pub fn wildcard(pattern: &str) -> Self {
+        Term::new(TermValue::Wildcard(pattern.to_owned()))
+    }
+
+    /// Matches terms starting with the prefix.
+    pub fn prefix(prefix: &str) -> Self {
+        Term::new(TermValue::Prefix(prefix.to_owned()))
+    }
+
+    /// Matches terms within the edit distance, at most 2.
+    pub fn fuzzy(text: &str, distance: u8) -> Self {
+        Term::new(TermValue::Fuzzy { text: text.to_owned(), distance: distance.min(MAX_EDIT_DISTANCE) })
+    }
+
+    /// Regular expression term, unescaped slashes in the pattern are escaped.
+    pub fn regex(pattern: &str) -> Self {
+        Term::new(TermValue::Regex(pattern.to_owned()))
+    }
+
+    /// Matches documents having any value in the field, rendered as `field:*`.
+    pub fn exists(field: &str) -> Self {
+        Term::new(TermValue::Exists).in_field(field)
+    }
+
+    /// Matches documents having any value in the field, rendered as `field:[* TO *]`.
+    pub fn exists_in_range(field: &str) -> Self {
+        Term::range(Range::unbounded()).in_field(field)
+    }
+
+    /// Range term, i.e. `Term::range(10..=20)` or `Term::range(Range::at_least(Date::now()))`.
+    pub fn range<R: Into<Range>>(range: R) -> Self {
+        Term::new(TermValue::Range(range.into()))
+    }
+
+    /// Trusted query syntax rendered without escaping, never use it with user input.
+    pub fn raw(raw: &str) -> Self {
+        Term::new(TermValue::Raw(raw.to_owned()))
+    }
+
+    pub fn new(value: TermValue) -> Self {
+        Term { field: None, value, modifiers: Modifiers::default() }
+    }
+
+    pub fn in_field(mut self, field: &str) -> Self {
+        self.field = Some(field.to_owned());
+        self
+    }
+
+    pub fn boost(mut self, value: f32) -> Self {
+        self.modifiers.set_boost(value);
+        self
+    }
+
+    /// Every match scores the value, rendered as `^=`.
+    pub fn constant_score(mut self, value: f32) -> Self {
+        self.modifiers.set_constant_score(value);
+        self
+    }
+
+    /// Sets the proximity of phrases, the maximum number of moves between their words.
+    /// Other values have no slop, it leaves them as they are.
+    pub fn slop(mut self, slop: u32) -> Self {
+        if let TermValue::Phrase { phrase, .. } = self.value {
+            self.value = TermValue::Phrase { phrase, slop: Some(slop) };
+        }
+        self
+    }
+
+    /// Slop of phrases, edit distance of text.
+    #[deprecated(since = "0.4.0", note = "Use Term::fuzzy or Term::slop instead.")]
+    pub fn tilde(self, value: u32) -> Self {
+        match self.value {
+            TermValue::Text(text) => Term { value: TermValue::Fuzzy { text, distance: value.min(MAX_EDIT_DISTANCE as u32) as u8 }, ..self },
+            _ => self.slop(value)
+        }
+    }
+
+    pub fn required(mut self) -> Self {
+        self.modifiers.occur = Some(Occur::Required);
+        self
+    }
+
+    pub fn prohibit(mut self) -> Self {
+        self.modifiers.occur = Some(Occur::Prohibited);
+        self
+    }
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.modifiers.prefix())?;
+        if let Some(field) = &self.field {
+            write!(f, "{}:", escape_query_chars(field))?;
+        }
+        write!(f, "{}{}", self.value, self.modifiers.suffix())
+    }
+}
+
+impl Stringable for Term {
+    fn as_str(&self) -> String {
+        self.to_string()
+    }
+
+    fn is_query(&self) -> bool {
+        false
+    }
+}
+
+/// Escapes the characters having meaning in the Lucene/Solr query syntax, and whitespace.
+pub fn escape_query_chars(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for char in value.chars() {
+        if "\\+-!():^[]\"{}~*?|&;/".contains(char) || char.is_whitespace() {
+            escaped.push('\\');
+        }
+        escaped.push(char);
+    }
+    escaped
+}
+
+/// Escapes the slashes not escaped yet, which would end the regular expression.
+fn escape_regex_delimiter(regex: &str) -> String {
+    let mut escaped = String::with_capacity(regex.len());
+    let mut backslash = false;
+    for char in regex.chars() {
+        if char == '/' && !backslash {
+            escaped.push('\\');
+        }
+        backslash = char == '\\' && !backslash;
+        escaped.push(char);
+    }
+    escaped
+}
+
+/// Escapes quotes and backslashes, the characters having meaning inside a phrase.
+pub(crate) fn escape_phrase(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Unit of date math offsets and rounding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DateUnit {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    Millisecond
+}
+
+impl DateUnit {
+    fn name(&self) -> &'static str {
+        match self {
+            DateUnit::Year => "YEAR",
+            DateUnit::Month => "MONTH",
+            DateUnit::Day => "DAY",
+            DateUnit::Hour => "HOUR",
+            DateUnit::Minute => "MINUTE",
+            DateUnit::Second => "SECOND",
+            DateUnit::Millisecond => "MILLISECOND"
+        }
+    }
+}
+
+impl Display for DateUnit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for DateUnit {
+    type Err = RSolrError;
+
+    /// Accepts the unit names of Solr date math, singular or plural.
+    fn from_str(unit: &str) -> Result<Self, Self::Err> {
+        match unit {
+            "YEAR" | "YEARS" => Ok(DateUnit::Year),
+            "MONTH" | "MONTHS" => Ok(DateUnit::Month),
+            "DAY" | "DAYS" | "DATE" => Ok(DateUnit::Day),
+            "HOUR" | "HOURS" => Ok(DateUnit::Hour),
+            "MINUTE" | "MINUTES" => Ok(DateUnit::Minute),
+            "SECOND" | "SECONDS" => Ok(DateUnit::Second),
+            "MILLI" | "MILLIS" | "MILLISECOND" | "MILLISECONDS" => Ok(DateUnit::Millisecond),
+            _ => Err(RSolrError::Validation(format!("unknown date unit `{}`", unit)))
+        }
+    }
+}
+
+/// Signed date offset, i.e. `+2MONTHS`. It's the gap of date range facets as well.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DateOffset {
+    pub amount: i64,
+    pub unit: DateUnit
+}
+
+impl DateOffset {
+
+    pub fn new(amount: i64, unit: DateUnit) -> Self {
+        DateOffset { amount, unit }
+    }
+
+    fn negate(self) -> Self {
+        DateOffset { amount: -self.amount, ..self }
+    }
+}
+
+impl Display for DateOffset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}S", if self.amount < 0 { '-' } else { '+' }, self.amount.unsigned_abs(), self.unit)
+    }
+}
+
+impl FromStr for DateOffset {
+    type Err = RSolrError;
+
+    fn from_str(offset: &str) -> Result<Self, Self::Err> {
+        match parse_date_math(offset)?.as_slice() {
+            [DateMath::Offset(offset)] => Ok(*offset),
+            _ => Err(RSolrError::Validation(format!("invalid date offset `{}`", offset)))
+        }
+    }
+}
+
+/// A step of date math: adding an offset or rounding down to a unit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DateMath {
+    Offset(DateOffset),
+    Round(DateUnit)
+}
+
+impl Display for DateMath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DateMath::Offset(offset) => write!(f, "{}", offset),
+            DateMath::Round(unit) => write!(f, "/{}", unit)
+        }
+    }
+}
+
+/// The date the math starts from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DateAnchor {
+    Now,
+    /// ISO-8601 timestamp in UTC, i.e. `2024-01-31T12:00:00Z`.
+    Instant(String),
+    /// Unvalidated expression of the deprecated Date::new, rendered as is.
+    Raw(String)
+}
+
+/// Solr date math expression, i.e. `NOW/DAY-7DAYS` or `2024-01-31T12:00:00Z+1MONTHS`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Date {
+    pub anchor: DateAnchor,
+    pub math: Vec<DateMath>
+}
+
+impl Date  {
+
+    pub fn year(count: u32) -> DateOffset {
+        DateOffset::new(count.into(), DateUnit::Year)
+    }
+
+    pub fn month(count: u32) -> DateOffset {
+        DateOffset::new(count.into(), DateUnit::Month)
+    }
+
+    pub fn day(count: u32) -> DateOffset {
+        DateOffset::new(count.into(), DateUnit::Day)
+    }
+
+    pub fn hour(count: u32) -> DateOffset {
+        DateOffset::new(count.into(), DateUnit::Hour)
+    }
+
+    pub fn minute(count: u32) -> DateOffset {
+        DateOffset::new(count.into(), DateUnit::Minute)
+    }
+
+    pub fn second(count: u32) -> DateOffset {
+        DateOffset::new(count.into(), DateUnit::Second)
+    }
+
+    pub fn millisecond(count: u32) -> DateOffset {
+        DateOffset::new(count.into(), DateUnit::Millisecond)
+    }
+
+    /// Parses a date math expression, an invalid one is kept as is.
+    #[deprecated(since = "0.4.0", note = "Use str::parse::<Date>() instead, it rejects invalid date math.")]
+    pub fn new(date_string: &str) -> Self {
+        date_string.parse().unwrap_or_else(|_| Date { anchor: DateAnchor::Raw(date_string.to_owned()), math: vec![] })
+    }
+
+    pub fn now() -> Self {
+        Date { anchor: DateAnchor::Now, math: vec![] }
+    }
+
+    /// Date from an ISO-8601 timestamp in UTC, i.e. `2024-01-31T12:00:00Z`.
+    pub fn instant(timestamp: &str) -> Result<Self, RSolrError> {
+        if !is_instant(timestamp) {
+            return Err(RSolrError::Validation(format!("`{}` is not an ISO-8601 UTC timestamp", timestamp)));
+        }
+        Ok(Date { anchor: DateAnchor::Instant(timestamp.to_owned()), math: vec![] })
+    }
+
+    pub fn plus(mut self, offset: DateOffset) -> Self {
+        self.math.push(DateMath::Offset(offset));
+        self
+    }
+
+    pub fn minus(mut self, offset: DateOffset) -> Self {
+        self.math.push(DateMath::Offset(offset.negate()));
+        self
+    }
+
+    /// Rounds down to the start of the unit, i.e. `/DAY`.
+    pub fn round(mut self, unit: DateUnit) -> Self {
+        self.math.push(DateMath::Round(unit));
+        self
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.anchor {
+            DateAnchor::Now => write!(f, "NOW")?,
+            DateAnchor::Instant(instant) | DateAnchor::Raw(instant) => write!(f, "{}", instant)?
+        }
+        for math in &self.math {
+            write!(f, "{}", math)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Date {
+    type Err = RSolrError;
+
+    fn from_str(date: &str) -> Result<Self, Self::Err> {
+        let (anchor, math) = match date.strip_prefix("NOW") {
+            Some(math) => (Date::now(), math),
+            None => {
+                let end = date.find('Z').map(|z| z + 1).unwrap_or(date.len());
+                (Date::instant(&date[..end])?, &date[end..])
+            }
+        };
+        Ok(Date { math: parse_date_math(math)?, ..anchor })
+    }
+}
+
+impl Stringable for Date {
+    fn as_str(&self) -> String {
+        self.to_string()
+    }
+
+    fn is_query(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> From<chrono::DateTime<Tz>> for Date {
+    fn from(instant: chrono::DateTime<Tz>) -> Self {
+        Date { anchor: DateAnchor::Instant(chrono_instant(&instant)), math: vec![] }
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Date {
+    fn from(instant: time::OffsetDateTime) -> Self {
+        Date { anchor: DateAnchor::Instant(time_instant(&instant)), math: vec![] }
+    }
+}
+
+#[cfg(feature = "chrono")]
+fn chrono_instant<Tz: chrono::TimeZone>(instant: &chrono::DateTime<Tz>) -> String {
+    instant.with_timezone(&chrono::Utc).to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+}
+
+#[cfg(feature = "time")]
+fn time_instant(instant: &time::OffsetDateTime) -> String {
+    instant.to_offset(time::UtcOffset::UTC)
+        .format(&time::format_description::well_known::Rfc3339)
+        .expect("UTC timestamp should be formattable as RFC 3339")
+}
+
+fn is_instant(timestamp: &str) -> bool {
+    static INSTANT: OnceLock<Regex> = OnceLock::new();
+    let regex = INSTANT.get_or_init(|| Regex::new(r"^(\d{4})-(\d{2})-(\d{2})T(\d{2}):(\d{2}):(\d{2})(\.\d{1,9})?Z$").unwrap());
+    match regex.captures(timestamp) {
+        Some(captures) => {
+            let field = |index: usize| captures[index].parse::<u32>().unwrap();
+            let (year, month, day) = (captures[1].parse::<i32>().unwrap(), field(2), field(3));
+            (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day) && field(4) < 24 && field(5) < 60 && field(6) < 60
+        },
+        None => false
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+fn parse_date_math(math: &str) -> Result<Vec<DateMath>, RSolrError> {
+    static DATE_MATH: OnceLock<Regex> = OnceLock::new();
+    let regex = DATE_MATH.get_or_init(|| Regex::new(r"^(?:([+-])(\d+)([A-Z]+)|/([A-Z]+))").unwrap());
+    let mut parsed = vec![];
+    let mut rest = math;
+    while !rest.is_empty() {
+        let captures = match regex.captures(rest) {
+            Some(captures) => captures,
+            None => return Err(RSolrError::Validation(format!("invalid date math at `{}`", rest)))
+        };
+        parsed.push(match captures.get(4) {
+            Some(unit) => DateMath::Round(unit.as_str().parse()?),
+            None => {
+                let amount = captures[2].parse::<i64>().map_err(|_| RSolrError::Validation(format!("date offset out of range at `{}`", rest)))?;
+                let offset = DateOffset::new(amount, captures[3].parse()?);
+                DateMath::Offset(if &captures[1] == "-" { offset.negate() } else { offset })
+            }
+        });
+        rest = &rest[captures[0].len()..];
+    }
+    Ok(parsed)
+}
+
+/// Values usable as range bounds, formatted the way Solr expects.
+pub trait RangeValue {
+    fn to_range_value(&self) -> String;
+
+    /// The value as the lower or the upper bound of a range, `None` if nothing can be beyond it.
+    fn to_bound(&self, inclusive: bool, _upper: bool) -> Option<Bound> {
+        let value = self.to_range_value();
+        Some(match inclusive {
+            true => Bound::Inclusive(value),
+            false => Bound::Exclusive(value)
+        })
+    }
+}
+
+macro_rules! display_range_value {
+    ($($type:ty),*) => {
+        $(impl RangeValue for $type {
+            fn to_range_value(&self) -> String {
+                self.to_string()
+            }
+        })*
+    };
+}
+
+display_range_value!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+macro_rules! float_range_value {
+    ($($type:ty),*) => {
+        $(impl RangeValue for $type {
+            /// Infinities are unbounded.
+            fn to_range_value(&self) -> String {
+                match self.is_infinite() {
+                    true => "*".to_owned(),
+                    false => self.to_string()
+                }
+            }
+
+            /// Infinities are unbounded on their own end, nothing is beyond NaN or an infinity on the other end.
+            fn to_bound(&self, inclusive: bool, upper: bool) -> Option<Bound> {
+                match (self.is_nan(), self.is_infinite()) {
+                    (true, _) => None,
+                    (false, true) if self.is_sign_positive() == upper => Some(Bound::Unbounded),
+                    (false, true) => None,
+                    (false, false) => Some(match inclusive {
+                        true => Bound::Inclusive(self.to_string()),
+                        false => Bound::Exclusive(self.to_string())
+                    })
+                }
+            }
+        })*
+    };
+}
+
+float_range_value!(f32, f64);
+
+impl RangeValue for str {
+    /// `*` is unbounded, values with whitespace, brackets, braces, quotes or backslashes are quoted.
+    fn to_range_value(&self) -> String {
+        if self == "*" {
+            return self.to_owned();
+        }
+        if self.is_empty() || self == "TO" || self.chars().any(|c| c.is_whitespace() || "[]{}\"\\".contains(c)) {
+            return format!("\"{}\"", escape_phrase(self));
+        }
+        self.to_owned()
+    }
+}
+
+impl RangeValue for String {
+    fn to_range_value(&self) -> String {
+        self.as_str().to_range_value()
     }
+}
 
-    fn is_query(&self) -> bool {
-        false
+impl RangeValue for Date {
+    fn to_range_value(&self) -> String {
+        self.to_string()
     }
 }
 
-pub struct Or {}
-impl Stringable for Or {
-    fn as_str(&self) -> String {
-        "OR".to_owned()
+impl<T: RangeValue + ?Sized> RangeValue for &T {
+    fn to_range_value(&self) -> String {
+        (**self).to_range_value()
     }
 
-    fn is_query(&self) -> bool {
-        false
+    fn to_bound(&self, inclusive: bool, upper: bool) -> Option<Bound> {
+        (**self).to_bound(inclusive, upper)
     }
 }
 
-pub struct Query {
-    parts: Vec<Box<dyn Stringable>>
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> RangeValue for chrono::DateTime<Tz> {
+    fn to_range_value(&self) -> String {
+        chrono_instant(self)
+    }
 }
 
-impl Query {
-
-    pub fn from_str(str: &str) -> Self {
-        let mut parts: Vec<Box<dyn Stringable>> = Vec::new();
-        parts.push(Box::new(Term::from_str(str)));
-        Query { parts }
+#[cfg(feature = "time")]
+impl RangeValue for time::OffsetDateTime {
+    fn to_range_value(&self) -> String {
+        time_instant(self)
     }
+}
 
-    pub fn from_term(term: Term) -> Self {
-        let mut parts: Vec<Box<dyn Stringable>> = Vec::new();
-        parts.push(Box::new(term));
-        Query { parts }
-    }
+/// One end of a range.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Bound {
+    Inclusive(String),
+    Exclusive(String),
+    Unbounded
+}
 
-    pub fn term(mut self, term: Term) -> Self {
-        self.parts.push(Box::new(term));
-        self
-    }
+impl Bound {
 
-    pub fn and(mut self) -> Self {
-        self.parts.push(Box::new(And {}));
-        self
+    pub fn inclusive<V: RangeValue>(value: V) -> Self {
+        Bound::Inclusive(value.to_range_value())
     }
 
-    pub fn or(mut self) -> Self {
-        self.parts.push(Box::new(Or {}));
-        self
+    pub fn exclusive<V: RangeValue>(value: V) -> Self {
+        Bound::Exclusive(value.to_range_value())
     }
 
-    pub fn subquery(mut self, query: Query) -> Self {
-        self.parts.push(Box::new(query));
-        self
+    fn value(&self) -> &str {
+        match self {
+            Bound::Inclusive(value) | Bound::Exclusive(value) => value,
+            Bound::Unbounded => "*"
+        }
     }
+}
 
+/// Range with inclusive, exclusive or unbounded ends, i.e. `[10 TO 20}` or `[NOW-1DAY TO *]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Range {
+    pub lower: Bound,
+    pub upper: Bound
 }
 
-impl Stringable for Query {
-    fn as_str(&self) -> String {
-        let mut query = "".to_owned();
-        for (_, item) in self.parts.iter().enumerate() {
-            query = match query.len() {
-                0 => item.as_str(),
-                _ => {
-                    match item.is_query() {
-                        true => format!("{} ({})", query, item.as_str()),
-                        false => format!("{} {}", query, item.as_str())
-                    }
-                }
-            }
-        }
-        query
+impl Range {
+
+    pub fn new(lower: Bound, upper: Bound) -> Self {
+        Range { lower, upper }
     }
 
-    fn is_query(&self) -> bool {
-        true
+    pub fn inclusive<F: RangeValue, T: RangeValue>(from: F, to: T) -> Self {
+        Range::between(from.to_bound(true, false), to.to_bound(true, true))
     }
-}
 
-pub struct Term {
-    term: String,
-    field: Option<String>
-}
+    pub fn exclusive<F: RangeValue, T: RangeValue>(from: F, to: T) -> Self {
+        Range::between(from.to_bound(false, false), to.to_bound(false, true))
+    }
 
-impl Term {
+    /// Values greater than or equal to the value.
+    pub fn at_least<V: RangeValue>(value: V) -> Self {
+        Range::between(value.to_bound(true, false), Some(Bound::Unbounded))
+    }
 
-    pub fn from_str(term_str: &str) -> Self {
-        if term_str.find(" ") != None {
-            return Term{ term: format!("\"{}\"", term_str.to_owned()), field: None };
-        }
-        Term{ term: term_str.to_owned(), field: None }
+    /// Values greater than the value.
+    pub fn greater_than<V: RangeValue>(value: V) -> Self {
+        Range::between(value.to_bound(false, false), Some(Bound::Unbounded))
     }
 
-    pub fn in_field(mut self, field: &str) -> Self {
-        self.field = Some(field.to_owned());
-        self
+    /// Values less than or equal to the value.
+    pub fn at_most<V: RangeValue>(value: V) -> Self {
+        Range::between(Some(Bound::Unbounded), value.to_bound(true, true))
     }
 
-    pub fn boost(mut self, value: f32) -> Self {
-        self.term = format!("{}^{}", self.term, value.to_string());
-        self
+    /// Values less than the value.
+    pub fn less_than<V: RangeValue>(value: V) -> Self {
+        Range::between(Some(Bound::Unbounded), value.to_bound(false, true))
     }
 
-    pub fn tilde(mut self, value: u32) -> Self {
-        self.term = format!("{}~{}", self.term, value.to_string());
-        self
+    /// Any value.
+    pub fn unbounded() -> Self {
+        Range::new(Bound::Unbounded, Bound::Unbounded)
     }
 
-    pub fn required(mut self) -> Self {
-        self.term = format!("+{}", self.term);
-        self
+    /// No value, rendered as `{0 TO 0}`: beyond NaN or infinite bounds on the wrong end.
+    pub fn empty() -> Self {
+        Range::new(Bound::Exclusive("0".to_owned()), Bound::Exclusive("0".to_owned()))
     }
 
-    pub fn prohibit(mut self) -> Self {
-        self.term = format!("-{}", self.term);
-        self
+    fn between(lower: Option<Bound>, upper: Option<Bound>) -> Self {
+        match (lower, upper) {
+            (Some(lower), Some(upper)) => Range::new(lower, upper),
+            _ => Range::empty()
+        }
     }
 }
 
-impl Stringable for Term {
+impl Display for Range {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let open = match self.lower {
+            Bound::Exclusive(_) => '{',
+            _ => '['
+        };
+        let close = match self.upper {
+            Bound::Exclusive(_) => '}',
+            _ => ']'
+        };
+        write!(f, "{}{} TO {}{}", open, self.lower.value(), self.upper.value(), close)
+    }
+}
+
+impl Stringable for Range {
     fn as_str(&self) -> String {
-        match &self.field {
-            Some(field) => format!("{}: {}", field, self.term),
-            None => self.term.clone()
-        }
+        self.to_string()
     }
 
     fn is_query(&self) -> bool {
@@ -165,91 +1288,125 @@ impl Stringable for Term {
     }
 }
 
-pub struct Date {
-    date: String
+impl<T: RangeValue> From<std::ops::RangeInclusive<T>> for Range {
+    fn from(range: std::ops::RangeInclusive<T>) -> Self {
+        let (start, end) = range.into_inner();
+        Range::inclusive(start, end)
+    }
 }
 
-impl Date  {
-
-    pub fn year(count: u32) -> String {
-        count.to_string() + "YEARS"
+impl<T: RangeValue> From<std::ops::Range<T>> for Range {
+    fn from(range: std::ops::Range<T>) -> Self {
+        Range::between(range.start.to_bound(true, false), range.end.to_bound(false, true))
     }
+}
 
-    pub fn month(count: u32) -> String {
-        count.to_string() + "MONTHS"
+impl<T: RangeValue> From<std::ops::RangeFrom<T>> for Range {
+    fn from(range: std::ops::RangeFrom<T>) -> Self {
+        Range::at_least(range.start)
     }
+}
 
-    pub fn day(count: u32) -> String {
-        count.to_string() + "DAYS"
+impl<T: RangeValue> From<std::ops::RangeTo<T>> for Range {
+    fn from(range: std::ops::RangeTo<T>) -> Self {
+        Range::less_than(range.end)
     }
+}
 
-    pub fn hour(count: u32) -> String {
-        count.to_string() + "HOURS"
+impl<T: RangeValue> From<std::ops::RangeToInclusive<T>> for Range {
+    fn from(range: std::ops::RangeToInclusive<T>) -> Self {
+        Range::at_most(range.end)
     }
+}
 
-    pub fn minute(count: u32) -> String {
-        count.to_string() + "MINUTES"
+impl From<std::ops::RangeFull> for Range {
+    fn from(_: std::ops::RangeFull) -> Self {
+        Range::unbounded()
     }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum ParamValue {
+    Literal(String),
+    Reference(String)
+}
+
+/// Local params, the `{!type key=value ...}` prefix of a query.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LocalParams {
+    parser: Option<String>,
+    params: Vec<(String, ParamValue)>
+}
+
+impl LocalParams {
 
-    pub fn second(count: u32) -> String {
-        count.to_string() + "SECONDS"
+    /// Local params switching to the query parser, i.e. `terms`, `lucene`, `frange`.
+    pub fn new(parser: &str) -> Self {
+        LocalParams { parser: Some(parser.to_owned()), params: vec![] }
     }
 
-    pub fn new(date_string: &str) -> Self {
-        Date { date: date_string.to_owned() }
+    /// Local params without parser, i.e. for `{!tag=x}` or `{!ex=x}`.
+    pub fn empty() -> Self {
+        LocalParams::default()
     }
 
-    pub fn plus(mut self, duration: &str) -> Self {
-        self.date = format!("{}+{}", self.date, duration);
+    /// Adds a parameter, the value is quoted if needed.
+    pub fn param(mut self, key: &str, value: &str) -> Self {
+        self.params.push((key.to_owned(), ParamValue::Literal(value.to_owned())));
         self
     }
 
-    pub fn minus(mut self, duration: &str) -> Self {
-        self.date = format!("{}-{}", self.date, duration);
+    /// Adds a parameter dereferencing a request parameter, rendered as `key=$param`.
+    pub fn deref(mut self, key: &str, param: &str) -> Self {
+        self.params.push((key.to_owned(), ParamValue::Reference(param.to_owned())));
         self
     }
-}
 
-impl Stringable for Date {
-    fn as_str(&self) -> String {
-        self.date.clone()
+    /// Sets the query with the `v` parameter instead of the text after the prefix.
+    pub fn value(self, query: &str) -> Self {
+        self.param("v", query)
     }
 
-    fn is_query(&self) -> bool {
-        false
+    pub fn is_empty(&self) -> bool {
+        self.parser.is_none() && self.params.is_empty()
     }
-}
-
-pub struct Range<'a> {
-    from: &'a str,
-    to: &'a str,
-    mode: Type
-}
 
-#[derive(PartialEq)]
-enum Type {
-    Inclusive,
-    Exclusive
-}
+    pub fn parser(&self) -> Option<&str> {
+        self.parser.as_deref()
+    }
 
-impl<'a> Range<'a> {
+    /// The params as key and value, none if any of them dereferences a request parameter.
+    pub(crate) fn literal_params(&self) -> Option<Vec<(&str, &str)>> {
+        self.params.iter().map(|(key, value)| match value {
+            ParamValue::Literal(literal) => Some((key.as_str(), literal.as_str())),
+            ParamValue::Reference(_) => None
+        }).collect()
+    }
 
-    pub fn inclusive(from: &'a str, to: &'a str) -> Self {
-        Range { from, to, mode: Type::Inclusive }
+    /// Renders the prefix followed by the query.
+    pub fn wrap(&self, query: &str) -> String {
+        format!("{}{}", self.as_str(), query)
     }
 
-    pub fn exclusive(from: &'a str, to: &'a str) -> Self {
-        Range { from, to, mode: Type::Exclusive }
+    /// The content between `{!` and `}`.
+    pub(crate) fn inner(&self) -> String {
+        let mut parts: Vec<String> = self.parser.iter().cloned().collect();
+        for (key, value) in &self.params {
+            parts.push(match value {
+                ParamValue::Literal(literal) => format!("{}={}", key, quote_param_value(literal)),
+                ParamValue::Reference(param) => format!("{}=${}", key, param)
+            });
+        }
+        parts.join(" ")
     }
 }
 
-impl<'a> Stringable for Range<'a> {
+impl Stringable for LocalParams {
     fn as_str(&self) -> String {
-        if self.mode == Type::Inclusive {
-            return format!("[{} TO {}]",self.from, self.to);
+        if self.is_empty() {
+            return "".to_owned();
         }
-
-        format!("{{{} TO {}}}",self.from, self.to)
+        format!("{{!{}}}", self.inner())
     }
 
     fn is_query(&self) -> bool {
@@ -257,38 +1414,52 @@ impl<'a> Stringable for Range<'a> {
     }
 }
 
+/// Quotes a local params value if it contains whitespace, quotes, braces or backslashes, or starts with `$`.
+pub(crate) fn quote_param_value(value: &str) -> String {
+    if value.is_empty() || value.starts_with('$') || value.chars().any(|c| c.is_whitespace() || "'\"}]\\".contains(c)) {
+        return format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"));
+    }
+    value.to_owned()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn query_create_from_str() {
-        assert_eq!(Query::from_str("*:*").as_str(), "*:*");
+        assert_eq!(Query::from_str("*:*").as_str(), "\\*\\:\\*");
+        assert_eq!(Query::from_str("a b) OR (c").as_str(), "\"a b) OR (c\"");
+    }
+
+    #[test]
+    fn query_create_raw() {
+        assert_eq!(Query::raw("*:*").as_str(), "*:*");
     }
 
     #[test]
     fn query_create_from_a_single_term() {
-        let term = Term::from_str("*:*");
+        let term = Term::raw("*:*");
         assert_eq!(Query::from_term(term).as_str(), "*:*");
     }
 
     #[test]
     fn query_concat_two_terms() {
-        let term = Term::from_str("*:*");
+        let term = Term::raw("*:*");
         let term2 = Term::from_str("another term");
         assert_eq!(Query::from_term(term).term(term2).as_str(), "*:* \"another term\"");
     }
 
     #[test]
     fn query_concat_two_terms_with_and() {
-        let term = Term::from_str("*:*");
+        let term = Term::raw("*:*");
         let term2 = Term::from_str("another term");
         assert_eq!(Query::from_term(term).and().term(term2).as_str(), "*:* AND \"another term\"");
     }
 
     #[test]
     fn query_concat_two_terms_with_or() {
-        let term = Term::from_str("*:*");
+        let term = Term::raw("*:*");
         let term2 = Term::from_str("another term");
         assert_eq!(Query::from_term(term).or().term(term2).as_str(), "*:* OR \"another term\"");
     }
@@ -304,6 +1475,124 @@ mod tests {
         assert_eq!(Query::from_term(term).or().subquery(query).as_str(), "\"another term\" OR (one_thing AND another_thing)");
     }
 
+    #[test]
+    fn query_is_comparable_and_cloneable() {
+        let query = Query::from_term(Term::from_str("a")).and().term(Term::from_str("b"));
+        assert_eq!(query.clone(), query);
+        assert_ne!(query, Query::from_term(Term::from_str("a")).or().term(Term::from_str("b")));
+        assert!(format!("{:?}", query).starts_with("Boolean("));
+    }
+
+    #[test]
+    fn query_built_as_tree() {
+        let query = Query::from_term(Term::from_str("a")).and().subquery(Query::from_str("b"));
+        assert_eq!(query, Query::Boolean(vec![
+            BooleanPart::Clause(Query::Term(Term::from_str("a"))),
+            BooleanPart::Operator(Operator::And),
+            BooleanPart::Clause(Query::Group(Group::new(Query::Term(Term::from_str("b")))))
+        ]));
+    }
+
+    #[test]
+    fn query_group_with_field_and_modifiers() {
+        let group = Group::new(Query::from_str("a").or().term(Term::from_str("b"))).in_field("title").boost(2.0).required();
+        assert_eq!(Query::from_str("c").group(group).to_string(), "c +title:(a OR b)^2");
+    }
+
+    #[test]
+    fn query_nested_boolean_parenthesized() {
+        let nested = Query::from_str("a").or().term(Term::from_str("b"));
+        let query = Query::Boolean(vec![BooleanPart::Clause(Query::from_str("c")), BooleanPart::Operator(Operator::And), BooleanPart::Clause(nested)]);
+        assert_eq!(query.to_string(), "c AND (a OR b)");
+    }
+
+    #[test]
+    fn query_with_not_operator() {
+        let query = Query::from_term(Term::from_str("a")).and().not().term(Term::from_str("b"));
+        assert_eq!(query.to_string(), "a AND NOT b");
+    }
+
+    #[test]
+    fn constant_score_replaces_boost() {
+        assert_eq!(Term::from_str("a").boost(2.0).constant_score(1.5).to_string(), "a^=1.5");
+        assert_eq!(Term::from_str("a").constant_score(1.5).boost(2.0).to_string(), "a^2");
+        assert_eq!(Group::new(Query::raw("a b")).constant_score(3.0).required().to_string(), "+(a b)^=3");
+    }
+
+    #[test]
+    fn query_boost_wraps_compound_queries() {
+        assert_eq!(Query::from_term(Term::from_str("a")).boost(2.0).to_string(), "a^2");
+        assert_eq!(Query::from_str("a").or().term(Term::from_str("b")).boost(2.0).to_string(), "(a OR b)^2");
+        let query = Query::from_str("c").and().subquery(Query::from_str("a").or().term(Term::from_str("b")).boost(3.0));
+        assert_eq!(query.to_string(), "c AND ((a OR b)^3)");
+    }
+
+    #[test]
+    fn boolean_group_renders_explicit_occurrences() {
+        let group = BooleanGroup::new()
+            .should(Term::from_str("a"))
+            .should(Term::from_str("b").required())
+            .must_not(Term::from_str("c"));
+        assert_eq!(Query::from(group).to_string(), "a OR b -c");
+
+        let group = BooleanGroup::new()
+            .must(Query::from_str("x").or().term(Term::from_str("y")))
+            .filter(Term::range(1..=5).in_field("n"))
+            .must_not(Term::from_str("z").prohibit());
+        assert_eq!(Query::from(group).to_string(), "+(x OR y) +filter(n:[1 TO 5]) -z");
+    }
+
+    #[test]
+    fn boolean_group_with_should_and_required_clauses_uses_bool_parser() {
+        let group = BooleanGroup::new().must(Term::from_str("a")).should(Term::phrase("b c"));
+        assert_eq!(Query::from(group).to_string(), "{!bool must=a should='\"b c\"'}");
+    }
+
+    #[test]
+    fn boolean_group_parenthesized_only_when_needed() {
+        let single = BooleanGroup::new().must(Term::from_str("a"));
+        let several = BooleanGroup::new().must(Term::from_str("a")).must(Term::from_str("b"));
+        let query = Query::from_str("c").or().subquery(single.clone().into()).or().term(Term::from_str("d"));
+        assert_eq!(query.to_string(), "c OR (+a) OR d");
+        let parts = vec![BooleanPart::Clause(Query::from_str("c")), BooleanPart::Operator(Operator::Or), BooleanPart::Clause(single.into())];
+        assert_eq!(Query::Boolean(parts).to_string(), "c OR (+a)");
+        let parts = vec![BooleanPart::Clause(Query::from_str("c")), BooleanPart::Operator(Operator::Or), BooleanPart::Clause(several.clone().into())];
+        assert_eq!(Query::Boolean(parts).to_string(), "c OR (+a +b)");
+        let outer = BooleanGroup::new().should(several).should(Term::from_str("d"));
+        assert_eq!(Query::from(outer).to_string(), "(+a +b) OR d");
+    }
+
+    #[test]
+    fn nested_single_clause_boolean_group_keeps_its_occurrence() {
+        let should = |group: BooleanGroup| BooleanGroup::new().should(Term::from_str("c")).should(group).should(Term::from_str("d"));
+        let must = BooleanGroup::new().must(Term::from_str("a"));
+        let filter = BooleanGroup::new().filter(Term::from_str("a"));
+        assert_eq!(Query::from(should(must.clone())).to_string(), "c OR (+a) OR d");
+        assert_eq!(Query::from(should(filter.clone())).to_string(), "c OR (+filter(a)) OR d");
+        assert_eq!(Query::from(BooleanGroup::new().must(Term::from_str("c")).must(must.clone())).to_string(), "+c +(+a)");
+        assert_eq!(Query::from(BooleanGroup::new().must(Term::from_str("c")).must_not(must)).to_string(), "+c -(+a)");
+        assert_eq!(Query::from(BooleanGroup::new().must(Term::from_str("c")).must(filter)).to_string(), "+c +(+filter(a))");
+        assert_eq!(Query::from(should(BooleanGroup::new().should(Term::from_str("a")))).to_string(), "c OR a OR d");
+    }
+
+    #[test]
+    fn nested_pure_negative_boolean_group_matches_all_first() {
+        let negative = BooleanGroup::new().must_not(Term::from_str("a"));
+        assert_eq!(Query::from(negative.clone()).to_string(), "-a");
+        let outer = BooleanGroup::new().must(Term::from_str("b")).must(negative.clone().boost(2.0));
+        assert_eq!(Query::from(outer).to_string(), "+b +(*:* -a)^2");
+        let outer = BooleanGroup::new().should(negative).should(Term::from_str("b"));
+        assert_eq!(Query::from(outer).to_string(), "(*:* -a) OR b");
+    }
+
+    #[test]
+    fn query_nested_local_params_take_the_query_in_v() {
+        let nested = Query::from_str("123").local_params(LocalParams::new("term").param("f", "id"));
+        let query = Query::from_str("a").and().subquery(nested.clone());
+        assert_eq!(nested.to_string(), "{!term f=id}123");
+        assert_eq!(query.to_string(), "a AND ({!term f=id v=123})");
+    }
+
     #[test]
     fn term_as_str_returns_term_as_str_in_quotes() {
         let term = "term term";
@@ -316,25 +1605,127 @@ mod tests {
         assert_eq!(Term::from_str(term).as_str(), term);
     }
 
+    #[test]
+    fn term_escapes_special_characters() {
+        assert_eq!(Term::from_str("a:b").to_string(), "a\\:b");
+        assert_eq!(Term::from_str("(x)").to_string(), "\\(x\\)");
+        assert_eq!(Term::from_str("1/2").to_string(), "1\\/2");
+        assert_eq!(Term::from_str("-x").to_string(), "\\-x");
+        assert_eq!(Term::from_str("a&&b||c").to_string(), "a\\&\\&b\\|\\|c");
+        assert_eq!(Term::from_str("back\\slash").to_string(), "back\\\\slash");
+        assert_eq!(Term::from_str("say\"").to_string(), "say\\\"");
+    }
+
+    #[test]
+    fn term_quotes_operator_words_and_empty_text() {
+        assert_eq!(Term::from_str("AND").to_string(), "\"AND\"");
+        assert_eq!(Term::from_str("").to_string(), "\"\"");
+        assert_eq!(Term::from_str("and").to_string(), "and");
+    }
+
+    #[test]
+    fn phrase_escapes_quotes_and_backslashes_only() {
+        assert_eq!(Term::from_str("say \"hi\" (now)").to_string(), "\"say \\\"hi\\\" (now)\"");
+        assert_eq!(Term::phrase("a\\b").to_string(), "\"a\\\\b\"");
+        assert_eq!(Term::phrase("single").to_string(), "\"single\"");
+    }
+
+    #[test]
+    fn term_from_injected_input_stays_a_single_clause() {
+        let query = Query::from_term(Term::from_str("x) OR (secret:true").in_field("title")).and().term(Term::from_str("y:z"));
+        assert_eq!(query.to_string(), "title:\"x) OR (secret:true\" AND y\\:z");
+    }
+
+    #[test]
+    fn raw_term_rendered_as_is() {
+        assert_eq!(Term::raw("title:(a OR b)").to_string(), "title:(a OR b)");
+    }
+
+    #[test]
+    fn field_name_escaped() {
+        assert_eq!(Term::from_str("x").in_field("my-field").to_string(), "my\\-field:x");
+    }
+
+    #[test]
+    fn escape_query_chars_escapes_all_special_characters_and_whitespace() {
+        assert_eq!(escape_query_chars("\\+-!():^[]\"{}~*?|&;/ x"), "\\\\\\+\\-\\!\\(\\)\\:\\^\\[\\]\\\"\\{\\}\\~\\*\\?\\|\\&\\;\\/\\ x");
+    }
+
     #[test]
     fn term_in_field_decorate_it_with_field() {
         let term_str = "term term";
         let term = Term::from_str(term_str);
-        assert_eq!(term.in_field("field").as_str(), "field: \"term term\"");
+        assert_eq!(term.in_field("field").as_str(), "field:\"term term\"");
     }
 
     #[test]
     fn term_boost_term_chained_with_field() {
         let term = Term::from_str("term term");
         let term_str = term.in_field("field").boost(3.2).as_str();
-        assert_eq!(term_str, "field: \"term term\"^3.2");
+        assert_eq!(term_str, "field:\"term term\"^3.2");
     }
 
     #[test]
+    #[allow(deprecated)]
     fn term_tilde_term_chained_with_boost() {
         let term = Term::from_str("term term");
         let term_str = term.boost(3.2).tilde(20).as_str();
-        assert_eq!(term_str, "\"term term\"^3.2~20");
+        assert_eq!(term_str, "\"term term\"~20^3.2");
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn term_tilde_on_text_is_fuzzy() {
+        assert_eq!(Term::from_str("roam").tilde(1).value, TermValue::Fuzzy { text: "roam".to_owned(), distance: 1 });
+    }
+
+    #[test]
+    fn term_modifiers_rendered_in_fixed_order() {
+        let term = Term::fuzzy("term", 1).boost(2.0).required().in_field("field");
+        assert_eq!(term.to_string(), "+field:term~1^2");
+        assert_eq!(term.modifiers, Modifiers { occur: Some(Occur::Required), boost: Some(2.0), constant_score: None });
+    }
+
+    #[test]
+    fn wildcard_escapes_everything_but_wildcards() {
+        assert_eq!(Term::wildcard("te?t*").to_string(), "te?t*");
+        assert_eq!(Term::wildcard("*a:b?c d").to_string(), "*a\\:b?c\\ d");
+    }
+
+    #[test]
+    fn prefix_escapes_and_appends_wildcard() {
+        assert_eq!(Term::prefix("c++").in_field("lang").to_string(), "lang:c\\+\\+*");
+        assert_eq!(Term::prefix("a*").to_string(), "a\\**");
+    }
+
+    #[test]
+    fn fuzzy_limits_edit_distance() {
+        assert_eq!(Term::fuzzy("roam", 1).to_string(), "roam~1");
+        assert_eq!(Term::fuzzy("ro:am", 5).to_string(), "ro\\:am~2");
+    }
+
+    #[test]
+    fn regex_wrapped_in_slashes() {
+        assert_eq!(Term::regex("ro[a-z]m").in_field("name").to_string(), "name:/ro[a-z]m/");
+        assert_eq!(Term::regex("a/b\\/c").to_string(), "/a\\/b\\/c/");
+    }
+
+    #[test]
+    fn phrase_slop() {
+        assert_eq!(Term::phrase("jakarta apache").slop(10).boost(2.0).to_string(), "\"jakarta apache\"~10^2");
+        assert_eq!(Term::from_str("text").slop(10).to_string(), "text");
+    }
+
+    #[test]
+    fn existence_terms() {
+        assert_eq!(Term::exists("field").to_string(), "field:*");
+        assert_eq!(Term::exists_in_range("field").prohibit().to_string(), "-field:[* TO *]");
+    }
+
+    #[test]
+    fn term_modifier_set_twice_keeps_the_last() {
+        let term = Term::from_str("term").required().prohibit().boost(2.0).boost(3.0);
+        assert_eq!(term.to_string(), "-term^3");
     }
 
     #[test]
@@ -352,25 +1743,96 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn date_as_str_returns_date() {
         let date_string = "NOW";
         assert_eq!(Date::new(date_string).as_str(), date_string);
     }
 
     #[test]
+    #[allow(deprecated)]
     fn date_plus_concat_text() {
         let date_string = "NOW";
         let expected = "NOW+2MONTHS";
         let date = Date::new(date_string);
-        assert_eq!(date.plus(Date::month(2).as_str()).as_str(), expected);
+        assert_eq!(date.plus(Date::month(2)).as_str(), expected);
     }
 
     #[test]
+    #[allow(deprecated)]
     fn date_minus_concat_text() {
         let date_string = "NOW";
         let expected = "NOW-2YEARS";
         let date = Date::new(date_string);
-        assert_eq!(date.minus(Date::year(2).as_str()).as_str(), expected);
+        assert_eq!(date.minus(Date::year(2)).as_str(), expected);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn date_new_keeps_invalid_expression() {
+        let date = Date::new("NOW+1WEEK");
+        assert_eq!(date.anchor, DateAnchor::Raw("NOW+1WEEK".to_owned()));
+        assert_eq!(date.minus(Date::day(1)).to_string(), "NOW+1WEEK-1DAYS");
+    }
+
+    #[test]
+    fn date_rounds_to_unit() {
+        let date = Date::now().round(DateUnit::Day).minus(Date::day(7)).plus(Date::hour(1));
+        assert_eq!(date.to_string(), "NOW/DAY-7DAYS+1HOURS");
+    }
+
+    #[test]
+    fn date_from_instant() {
+        assert!(Date::instant("2024-02-29T00:00:00Z").is_ok());
+        assert!(Date::instant("2000-02-29T00:00:00Z").is_ok());
+        let date = Date::instant("2024-01-31T12:00:00.250Z").unwrap().plus(Date::month(1)).round(DateUnit::Month);
+        assert_eq!(date.to_string(), "2024-01-31T12:00:00.250Z+1MONTHS/MONTH");
+    }
+
+    #[test]
+    fn date_rejects_invalid_instant() {
+        for instant in ["2024-01-31", "2024-13-01T00:00:00Z", "2024-01-31T12:00:00+01:00", "2024-01-31T24:00:00Z",
+            "2024-02-30T00:00:00Z", "2023-02-29T00:00:00Z", "1900-02-29T00:00:00Z", "2024-04-31T00:00:00Z", "2024-01-00T00:00:00Z"] {
+            assert!(matches!(Date::instant(instant), Err(RSolrError::Validation(..))), "{}", instant);
+        }
+    }
+
+    #[test]
+    fn date_parsed_from_date_math() {
+        let date: Date = "NOW/DAY-7DAYS+1HOUR/MINUTE".parse().unwrap();
+        assert_eq!(date, Date::now().round(DateUnit::Day).minus(Date::day(7)).plus(Date::hour(1)).round(DateUnit::Minute));
+        let date: Date = "2024-01-31T12:00:00Z-1YEAR".parse().unwrap();
+        assert_eq!(date.anchor, DateAnchor::Instant("2024-01-31T12:00:00Z".to_owned()));
+        assert_eq!(date.math, vec![DateMath::Offset(DateOffset::new(-1, DateUnit::Year))]);
+    }
+
+    #[test]
+    fn date_parse_rejects_invalid_math() {
+        for date in ["", "TODAY", "NOW+DAY", "NOW+1WEEK", "NOW/", "NOW-2MONTHS+", "NOW 1DAY", "2024-01-31T12:00:00"] {
+            assert!(date.parse::<Date>().is_err(), "{}", date);
+        }
+    }
+
+    #[test]
+    fn date_offset_is_a_range_facet_gap() {
+        assert_eq!(Date::day(1).to_string(), "+1DAYS");
+        assert_eq!("-3MONTHS".parse::<DateOffset>().unwrap(), DateOffset::new(-3, DateUnit::Month));
+        assert!("/DAY".parse::<DateOffset>().is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn date_from_chrono() {
+        use chrono::TimeZone;
+        let instant = chrono::FixedOffset::west_opt(7200).unwrap().with_ymd_and_hms(2024, 1, 31, 22, 0, 0).unwrap();
+        assert_eq!(Date::from(instant).round(DateUnit::Day).to_string(), "2024-02-01T00:00:00Z/DAY");
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn date_from_time() {
+        let instant = time::Date::from_calendar_date(2024, time::Month::January, 31).unwrap().with_hms(22, 0, 0).unwrap().assume_utc();
+        assert_eq!(Date::from(instant).plus(Date::day(1)).to_string(), "2024-01-31T22:00:00Z+1DAYS");
     }
 
     #[test]
@@ -384,4 +1846,124 @@ mod tests {
         let range = Range::exclusive("a", "b");
         assert_eq!(range.as_str(), "{a TO b}");
     }
+
+    #[test]
+    fn range_with_mixed_inclusivity() {
+        assert_eq!(Range::new(Bound::inclusive(10), Bound::exclusive(20)).to_string(), "[10 TO 20}");
+        assert_eq!(Range::new(Bound::exclusive(10), Bound::inclusive(20)).to_string(), "{10 TO 20]");
+    }
+
+    #[test]
+    fn range_with_unbounded_ends() {
+        assert_eq!(Range::at_least(10).to_string(), "[10 TO *]");
+        assert_eq!(Range::greater_than(10).to_string(), "{10 TO *]");
+        assert_eq!(Range::at_most(-1.5).to_string(), "[* TO -1.5]");
+        assert_eq!(Range::less_than(20_u64).to_string(), "[* TO 20}");
+        assert_eq!(Range::unbounded().to_string(), "[* TO *]");
+        assert_eq!(Range::inclusive(f64::NEG_INFINITY, 1).to_string(), "[* TO 1]");
+        assert_eq!(Range::exclusive(f64::NEG_INFINITY, f64::INFINITY).to_string(), "[* TO *]");
+        assert_eq!(Range::from(..=f32::INFINITY).to_string(), "[* TO *]");
+    }
+
+    #[test]
+    fn range_beyond_nan_or_infinities_on_the_wrong_end_is_empty() {
+        assert_eq!(Range::empty().to_string(), "{0 TO 0}");
+        assert_eq!(Range::greater_than(f64::INFINITY), Range::empty());
+        assert_eq!(Range::at_least(f64::INFINITY), Range::empty());
+        assert_eq!(Range::less_than(f64::NEG_INFINITY), Range::empty());
+        assert_eq!(Range::inclusive(1.0, f64::NEG_INFINITY), Range::empty());
+        assert_eq!(Range::from(f64::NAN..1.0), Range::empty());
+        assert_eq!(Range::at_most(f32::NAN), Range::empty());
+    }
+
+    #[test]
+    fn range_quotes_string_bounds_when_needed() {
+        assert_eq!(Range::inclusive("a b", "c]").to_string(), "[\"a b\" TO \"c]\"]");
+        assert_eq!(Range::inclusive("say \"x\"", "TO").to_string(), "[\"say \\\"x\\\"\" TO \"TO\"]");
+        assert_eq!(Range::inclusive("*", "2024-01-01T00:00:00Z".to_owned()).to_string(), "[* TO 2024-01-01T00:00:00Z]");
+    }
+
+    #[test]
+    fn range_with_date_bounds() {
+        let range = Range::inclusive(Date::now().minus(Date::year(1)), Date::now());
+        assert_eq!(range.to_string(), "[NOW-1YEARS TO NOW]");
+    }
+
+    #[test]
+    fn range_from_std_ranges() {
+        assert_eq!(Range::from(1..=5).to_string(), "[1 TO 5]");
+        assert_eq!(Range::from(1..5).to_string(), "[1 TO 5}");
+        assert_eq!(Range::from(1.5..).to_string(), "[1.5 TO *]");
+        assert_eq!(Range::from(..5).to_string(), "[* TO 5}");
+        assert_eq!(Range::from(..=5).to_string(), "[* TO 5]");
+        assert_eq!(Range::from(..).to_string(), "[* TO *]");
+        assert_eq!(Range::from("a".."m").to_string(), "[a TO m}");
+    }
+
+    #[test]
+    fn range_plugs_into_term() {
+        assert_eq!(Term::range(10..=20).in_field("price").boost(2.0).to_string(), "price:[10 TO 20]^2");
+        assert_eq!(Term::from(Range::at_least(3)).in_field("rating").to_string(), "rating:[3 TO *]");
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn range_with_chrono_bounds() {
+        use chrono::TimeZone;
+        let from = chrono::Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        let to = chrono::FixedOffset::east_opt(3600).unwrap().with_ymd_and_hms(2024, 2, 1, 1, 0, 0).unwrap();
+        assert_eq!(Range::inclusive(from, to).to_string(), "[2024-01-02T03:04:05Z TO 2024-02-01T00:00:00Z]");
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn range_with_time_bounds() {
+        let date = time::Date::from_calendar_date(2024, time::Month::January, 2).unwrap();
+        let from = date.with_hms(3, 4, 5).unwrap().assume_utc();
+        let to = date.with_hms_milli(4, 4, 5, 500).unwrap().assume_offset(time::UtcOffset::from_hms(1, 0, 0).unwrap());
+        assert_eq!(Range::inclusive(from, to).to_string(), "[2024-01-02T03:04:05Z TO 2024-01-02T03:04:05.5Z]");
+    }
+
+    #[test]
+    fn local_params_render_parser_and_params() {
+        let local_params = LocalParams::new("lucene").param("q.op", "AND").param("df", "title");
+        assert_eq!(local_params.as_str(), "{!lucene q.op=AND df=title}");
+    }
+
+    #[test]
+    fn local_params_quote_values() {
+        let local_params = LocalParams::new("parent")
+            .param("which", "type:parent AND x:'y'")
+            .param("empty", "")
+            .param("literal", "$not_a_reference")
+            .param("brace", "a}b");
+        assert_eq!(local_params.as_str(), "{!parent which='type:parent AND x:\\'y\\'' empty='' literal='$not_a_reference' brace='a}b'}");
+    }
+
+    #[test]
+    fn local_params_dereference_params() {
+        let local_params = LocalParams::new("terms").param("f", "id").deref("v", "ids");
+        assert_eq!(local_params.as_str(), "{!terms f=id v=$ids}");
+    }
+
+    #[test]
+    fn local_params_without_parser() {
+        assert_eq!(LocalParams::empty().param("ex", "type").wrap("type"), "{!ex=type}type");
+        assert_eq!(LocalParams::empty().wrap("type"), "type");
+    }
+
+    #[test]
+    fn local_params_value_param() {
+        let local_params = LocalParams::new("frange").param("l", "0").param("u", "10").value("log(popularity)");
+        assert_eq!(local_params.as_str(), "{!frange l=0 u=10 v=log(popularity)}");
+    }
+
+    #[test]
+    fn query_prefixed_with_local_params() {
+        let query = Query::from_term(Term::from_str("title"))
+            .and()
+            .term(Term::from_str("body"))
+            .local_params(LocalParams::new("lucene").param("q.op", "OR"));
+        assert_eq!(query.as_str(), "{!lucene q.op=OR}title AND body");
+    }
 }
\ No newline at end of file
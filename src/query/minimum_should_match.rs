@@ -0,0 +1,110 @@
+//! The minimum should match spec of boolean groups and the DisMax parsers.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::error::RSolrError;
+
+/// Validated 'mm' spec: a count (`3`, `-2`), a percentage (`75%`, `-25%`) or conditions (`2<-25% 9<-3`).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MinimumShouldMatch(String);
+
+impl MinimumShouldMatch {
+
+    /// Clauses that must match, negative means all but that many.
+    pub fn count(count: i32) -> Self {
+        MinimumShouldMatch(count.to_string())
+    }
+
+    /// Percentage of clauses that must match, negative means all but that many percent.
+    pub fn percent(percent: i8) -> Self {
+        MinimumShouldMatch(format!("{}%", percent.clamp(-100, 100)))
+    }
+}
+
+impl Display for MinimumShouldMatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for MinimumShouldMatch {
+    type Err = RSolrError;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| RSolrError::Validation(format!("invalid mm `{}`, {}", spec, reason));
+        let clauses: Vec<&str> = spec.split_whitespace().collect();
+        if clauses.is_empty() {
+            return Err(invalid("it's empty"));
+        }
+
+        let mut last_condition = 0;
+        for clause in &clauses {
+            let value = match clause.split_once('<') {
+                Some((condition, value)) => {
+                    let condition = match condition.parse::<u32>() {
+                        Ok(condition) if condition > 0 => condition,
+                        _ => return Err(invalid("condition must be a positive integer"))
+                    };
+                    if condition <= last_condition {
+                        return Err(invalid("conditions must be increasing"));
+                    }
+                    last_condition = condition;
+                    value
+                },
+                None if clauses.len() == 1 => clause,
+                None => return Err(invalid("every clause needs a condition when there are more"))
+            };
+            match value.strip_suffix('%') {
+                Some(percent) => match percent.parse::<i32>() {
+                    Ok(percent) if (-100..=100).contains(&percent) => (),
+                    _ => return Err(invalid("percentage must be between -100% and 100%"))
+                },
+                None => {
+                    value.parse::<i32>().map_err(|_| invalid("value must be an integer or percentage"))?;
+                }
+            }
+        }
+        Ok(MinimumShouldMatch(clauses.join(" ")))
+    }
+}
+
+impl TryFrom<String> for MinimumShouldMatch {
+    type Error = RSolrError;
+
+    fn try_from(spec: String) -> Result<Self, Self::Error> {
+        spec.parse()
+    }
+}
+
+impl From<MinimumShouldMatch> for String {
+    fn from(mm: MinimumShouldMatch) -> Self {
+        mm.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mm_accepts_valid_specs() {
+        for spec in ["3", "-2", "75%", "-25%", "2<-25%", "3<90% 9<-1", "2<-25% 9<-3"] {
+            assert_eq!(spec.parse::<MinimumShouldMatch>().expect(spec).to_string(), spec);
+        }
+    }
+
+    #[test]
+    fn mm_rejects_invalid_specs() {
+        for spec in ["", "abc", "120%", "2<", "<3", "2<x", "3 4", "9<-3 2<-25%", "-1<2", "0<90%", "0<1 3<2"] {
+            assert!(matches!(spec.parse::<MinimumShouldMatch>(), Err(RSolrError::Validation(..))), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn mm_constructors() {
+        assert_eq!(MinimumShouldMatch::count(-2).to_string(), "-2");
+        assert_eq!(MinimumShouldMatch::percent(75).to_string(), "75%");
+    }
+}
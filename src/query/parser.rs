@@ -0,0 +1,555 @@
+//! Parser of the standard query parser syntax into the query tree.
+
+use crate::error::RSolrError;
+use super::{BooleanPart, Bound, Group, LocalParams, Occur, Operator, ParamValue, Query, Range, Term, TermValue};
+
+/// Characters ending a bare term, besides whitespace.
+const TERM_END: &str = "()[]{}^~\":";
+
+pub(super) fn parse(query: &str) -> Result<Query, RSolrError> {
+    let mut parser = Parser { chars: query.chars().collect(), position: 0 };
+    parser.skip_whitespace();
+    if !parser.starts_with("{!") {
+        return parser.boolean(false);
+    }
+
+    let local_params = parser.local_params()?;
+    let query = match local_params.parser.as_deref() {
+        None | Some("lucene") => {
+            parser.skip_whitespace();
+            match parser.peek() {
+                None => Query::Term(Term::raw("")),
+                Some(_) => parser.boolean(false)?
+            }
+        },
+        _ => Query::Term(Term::raw(&parser.rest()))
+    };
+    Ok(query.local_params(local_params))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize
+}
+
+impl Parser {
+
+    /// Clauses and operators up to the end, or up to the closing parenthesis of a group.
+    fn boolean(&mut self, in_group: bool) -> Result<Query, RSolrError> {
+        let start = self.position;
+        let mut parts: Vec<BooleanPart> = vec![];
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None if in_group => return Err(self.error(self.position, "expected `)`")),
+                None => break,
+                Some(')') if in_group => break,
+                Some(')') => return Err(self.error(self.position, "unexpected `)`")),
+                _ => ()
+            }
+
+            let operator_start = self.position;
+            if let Some(operator) = self.operator() {
+                let valid = match (operator, parts.last()) {
+                    (Operator::Not, Some(BooleanPart::Operator(Operator::Not))) => false,
+                    (Operator::Not, _) => true,
+                    (_, last) => matches!(last, Some(BooleanPart::Clause(_)))
+                };
+                if !valid {
+                    return Err(self.error(operator_start, &format!("`{}` is not preceded by a clause", operator)));
+                }
+                parts.push(BooleanPart::Operator(operator));
+                continue;
+            }
+            parts.push(BooleanPart::Clause(self.clause()?));
+        }
+
+        if let Some(BooleanPart::Operator(operator)) = parts.last() {
+            return Err(self.error(self.position, &format!("expected a clause after `{}`", operator)));
+        }
+        match parts.len() {
+            0 => Err(self.error(start, "expected a clause")),
+            1 => match parts.pop() {
+                Some(BooleanPart::Clause(query)) => Ok(query),
+                _ => unreachable!("a single part is a clause")
+            },
+            _ => Ok(Query::Boolean(parts))
+        }
+    }
+
+    fn operator(&mut self) -> Option<Operator> {
+        for (token, operator) in [("&&", Operator::And), ("||", Operator::Or), ("!", Operator::Not)] {
+            if self.starts_with(token) {
+                self.position += token.len();
+                return Some(operator);
+            }
+        }
+        for (keyword, operator) in [("AND", Operator::And), ("OR", Operator::Or), ("NOT", Operator::Not)] {
+            let next = self.chars.get(self.position + keyword.len());
+            if self.starts_with(keyword) && !next.is_some_and(|c| is_term_char(*c)) {
+                self.position += keyword.len();
+                return Some(operator);
+            }
+        }
+        None
+    }
+
+    fn clause(&mut self) -> Result<Query, RSolrError> {
+        let occur = match self.peek() {
+            Some('+') => Some(Occur::Required),
+            Some('-') => Some(Occur::Prohibited),
+            _ => None
+        };
+        if occur.is_some() {
+            self.position += 1;
+            self.skip_whitespace();
+        }
+
+        let query = match self.starts_with("{!") {
+            true => self.nested_local_params()?,
+            false => {
+                let field = self.field()?;
+                self.value(field)?
+            }
+        };
+        Ok(match (occur, query) {
+            (None, query) => query,
+            (Some(occur), Query::Term(mut term)) => {
+                term.modifiers.occur = Some(occur);
+                Query::Term(term)
+            },
+            (Some(occur), Query::Group(mut group)) => {
+                group.modifiers.occur = Some(occur);
+                Query::Group(group)
+            },
+            (Some(occur), query) => {
+                let mut group = Group::new(query);
+                group.modifiers.occur = Some(occur);
+                Query::Group(group)
+            }
+        })
+    }
+
+    /// The field of the clause if the next term ends with a colon.
+    fn field(&mut self) -> Result<Option<String>, RSolrError> {
+        let start = self.position;
+        if !self.peek().is_some_and(is_term_char) {
+            return Ok(None);
+        }
+        let (field, ..) = self.bare_term()?;
+        if self.peek() == Some(':') {
+            self.position += 1;
+            self.skip_whitespace();
+            return Ok(Some(field));
+        }
+        self.position = start;
+        Ok(None)
+    }
+
+    fn value(&mut self, field: Option<String>) -> Result<Query, RSolrError> {
+        let start = self.position;
+        let is_term = self.peek().is_some_and(|c| is_term_char(c) && c != '/');
+        let mut term = match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let query = self.boolean(true)?;
+                self.position += 1;
+                let mut group = Group::new(query);
+                group.field = field;
+                self.boost(&mut group.modifiers)?;
+                return Ok(Query::Group(group));
+            },
+            Some('"') => Term::phrase(&self.quoted('"', "unterminated phrase")?),
+            Some('[') | Some('{') => Term::range(self.range()?),
+            Some('/') => Term::new(TermValue::Regex(self.regex()?)),
+            Some(_) if is_term => self.term(field.clone())?,
+            Some(c) => return Err(self.error(start, &format!("unexpected `{}`", c))),
+            None => return Err(self.error(start, "expected a value"))
+        };
+        if !is_term {
+            term.field = field;
+        }
+
+        if self.peek() == Some('~') {
+            let tilde = self.position;
+            self.position += 1;
+            let number = self.number();
+            term.value = match term.value {
+                TermValue::Phrase { phrase, .. } => TermValue::Phrase { phrase, slop: Some(self.whole_number(&number, tilde)?) },
+                TermValue::Text(text) => match number.is_empty() {
+                    true => Term::fuzzy(&text, super::MAX_EDIT_DISTANCE).value,
+                    false => Term::fuzzy(&text, self.whole_number(&number, tilde)?.min(u8::MAX.into()) as u8).value
+                },
+                _ => return Err(self.error(tilde, "`~` follows only terms and phrases"))
+            };
+        }
+        self.boost(&mut term.modifiers)?;
+        Ok(Query::Term(term))
+    }
+
+    /// Text, wildcard, prefix or exists term in the field, with escapes resolved.
+    fn term(&mut self, field: Option<String>) -> Result<Term, RSolrError> {
+        let (text, source, wildcards, escaped_wildcard) = self.bare_term()?;
+        let value = match (field.as_deref(), text.as_str()) {
+            (Some("*"), "*") if wildcards == 1 => return Ok(Term::raw("*:*")),
+            (Some(_), "*") if wildcards == 1 => TermValue::Exists,
+            _ if wildcards == 0 => TermValue::Text(text),
+            _ if escaped_wildcard => TermValue::Raw(source),
+            _ if wildcards == 1 && text.ends_with('*') => TermValue::Prefix(text[..text.len() - 1].to_owned()),
+            _ => TermValue::Wildcard(text)
+        };
+        Ok(Term { field, ..Term::new(value) })
+    }
+
+    /// Unescaped text, source, the count of unescaped wildcards and whether there are escaped ones.
+    fn bare_term(&mut self) -> Result<(String, String, usize, bool), RSolrError> {
+        let start = self.position;
+        let mut text = String::new();
+        let mut wildcards = 0;
+        let mut escaped_wildcard = false;
+        while let Some(c) = self.peek().filter(|c| is_term_char(*c)) {
+            self.position += 1;
+            match c {
+                '\\' => match self.peek() {
+                    Some(escaped) => {
+                        escaped_wildcard |= escaped == '*' || escaped == '?';
+                        text.push(escaped);
+                        self.position += 1;
+                    },
+                    None => return Err(self.error(self.position - 1, "nothing to escape after `\\`"))
+                },
+                '*' | '?' => {
+                    wildcards += 1;
+                    text.push(c);
+                },
+                c => text.push(c)
+            }
+        }
+        Ok((text, self.chars[start..self.position].iter().collect(), wildcards, escaped_wildcard))
+    }
+
+    /// Content between the quotes with escapes resolved.
+    fn quoted(&mut self, quote: char, unterminated: &str) -> Result<String, RSolrError> {
+        let start = self.position;
+        self.position += 1;
+        let mut content = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error(start, unterminated)),
+                Some('\\') if self.position + 1 < self.chars.len() => {
+                    content.push(self.chars[self.position + 1]);
+                    self.position += 2;
+                },
+                Some(c) if c == quote => {
+                    self.position += 1;
+                    return Ok(content);
+                },
+                Some(c) => {
+                    content.push(c);
+                    self.position += 1;
+                }
+            }
+        }
+    }
+
+    /// Pattern between the slashes, as written.
+    fn regex(&mut self) -> Result<String, RSolrError> {
+        let start = self.position;
+        self.position += 1;
+        let mut backslash = false;
+        while let Some(c) = self.peek() {
+            self.position += 1;
+            if c == '/' && !backslash {
+                return Ok(self.chars[start + 1..self.position - 1].iter().collect());
+            }
+            backslash = c == '\\' && !backslash;
+        }
+        Err(self.error(start, "unterminated regular expression"))
+    }
+
+    fn range(&mut self) -> Result<Range, RSolrError> {
+        let start = self.position;
+        let lower_inclusive = self.peek() == Some('[');
+        self.position += 1;
+        self.skip_whitespace();
+        let lower = self.range_bound()?;
+        self.skip_whitespace();
+        if !self.starts_with("TO") {
+            return Err(self.error(self.position, "expected `TO`"));
+        }
+        self.position += 2;
+        self.skip_whitespace();
+        let upper = self.range_bound()?;
+        self.skip_whitespace();
+        let upper_inclusive = match self.peek() {
+            Some(']') => true,
+            Some('}') => false,
+            None => return Err(self.error(start, "unterminated range")),
+            Some(_) => return Err(self.error(self.position, "expected `]` or `}`"))
+        };
+        self.position += 1;
+        let bound = |value: Option<String>, inclusive: bool| match (value, inclusive) {
+            (None, _) => Bound::Unbounded,
+            (Some(value), true) => Bound::Inclusive(value),
+            (Some(value), false) => Bound::Exclusive(value)
+        };
+        Ok(Range::new(bound(lower, lower_inclusive), bound(upper, upper_inclusive)))
+    }
+
+    /// The bound as written, none if it's `*`.
+    fn range_bound(&mut self) -> Result<Option<String>, RSolrError> {
+        let start = self.position;
+        if self.peek() == Some('"') {
+            self.quoted('"', "unterminated range bound")?;
+        } else {
+            while self.peek().is_some_and(|c| !c.is_whitespace() && c != ']' && c != '}') {
+                self.position += 1;
+            }
+        }
+        let bound: String = self.chars[start..self.position].iter().collect();
+        match bound.as_str() {
+            "" => Err(self.error(start, "expected a range bound")),
+            "*" => Ok(None),
+            _ => Ok(Some(bound))
+        }
+    }
+
+    /// `^boost` or `^=score`.
+    fn boost(&mut self, modifiers: &mut super::Modifiers) -> Result<(), RSolrError> {
+        if self.peek() != Some('^') {
+            return Ok(());
+        }
+        let caret = self.position;
+        self.position += 1;
+        let constant = self.peek() == Some('=');
+        if constant {
+            self.position += 1;
+        }
+        let value: f32 = self.number().parse().map_err(|_| self.error(caret, "expected a number after `^`"))?;
+        match constant {
+            true => modifiers.set_constant_score(value),
+            false => modifiers.set_boost(value)
+        }
+        Ok(())
+    }
+
+    fn number(&mut self) -> String {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    fn whole_number(&self, number: &str, position: usize) -> Result<u32, RSolrError> {
+        number.parse().map_err(|_| self.error(position, "expected a whole number after `~`"))
+    }
+
+    /// A `{!...}` clause inside the query, the text up to the next whitespace is its value.
+    fn nested_local_params(&mut self) -> Result<Query, RSolrError> {
+        let local_params = self.local_params()?;
+        let start = self.position;
+        while self.peek().is_some_and(|c| !c.is_whitespace() && c != ')') {
+            self.position += 1;
+        }
+        let value: String = self.chars[start..self.position].iter().collect();
+        Ok(Query::Term(Term::raw(&value)).local_params(local_params))
+    }
+
+    fn local_params(&mut self) -> Result<LocalParams, RSolrError> {
+        let start = self.position;
+        self.position += 2;
+        let mut local_params = LocalParams::empty();
+        let mut first = true;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => return Err(self.error(start, "unterminated local params")),
+                Some('}') => {
+                    self.position += 1;
+                    return Ok(local_params);
+                },
+                _ => ()
+            }
+
+            let key_start = self.position;
+            while self.peek().is_some_and(|c| !c.is_whitespace() && c != '=' && c != '}') {
+                self.position += 1;
+            }
+            let key: String = self.chars[key_start..self.position].iter().collect();
+            if key.is_empty() {
+                return Err(self.error(key_start, "expected a parameter name"));
+            }
+            if self.peek() != Some('=') {
+                if !first {
+                    return Err(self.error(self.position, &format!("expected `=` after `{}`", key)));
+                }
+                local_params.parser = Some(key);
+                first = false;
+                continue;
+            }
+            self.position += 1;
+            let value = match self.peek() {
+                Some(quote @ ('\'' | '"')) => ParamValue::Literal(self.quoted(quote, "unterminated parameter value")?),
+                _ => {
+                    let value_start = self.position;
+                    while self.peek().is_some_and(|c| !c.is_whitespace() && c != '}') {
+                        self.position += 1;
+                    }
+                    let value: String = self.chars[value_start..self.position].iter().collect();
+                    match value.strip_prefix('$') {
+                        Some(param) => ParamValue::Reference(param.to_owned()),
+                        None => ParamValue::Literal(value)
+                    }
+                }
+            };
+            local_params.params.push((key, value));
+            first = false;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn starts_with(&self, token: &str) -> bool {
+        token.chars().enumerate().all(|(index, c)| self.chars.get(self.position + index) == Some(&c))
+    }
+
+    fn rest(&mut self) -> String {
+        let rest = self.chars[self.position..].iter().collect();
+        self.position = self.chars.len();
+        rest
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn error(&self, position: usize, message: &str) -> RSolrError {
+        RSolrError::QueryParse { message: message.to_owned(), position }
+    }
+}
+
+fn is_term_char(c: char) -> bool {
+    !c.is_whitespace() && !TERM_END.contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::{BooleanGroup, Query};
+    use super::*;
+
+    fn round_trip(query: &str) {
+        assert_eq!(Query::parse(query).expect(query).to_string(), query);
+    }
+
+    fn error_position(query: &str) -> usize {
+        match Query::parse(query) {
+            Err(RSolrError::QueryParse { position, .. }) => position,
+            other => panic!("{} parsed as {:?}", query, other)
+        }
+    }
+
+    #[test]
+    fn parses_terms_and_fields() {
+        assert_eq!(Query::parse("rust").unwrap(), Query::Term(Term::from_str("rust")));
+        assert_eq!(Query::parse("title:rust").unwrap(), Query::Term(Term::from_str("rust").in_field("title")));
+        assert_eq!(Query::parse("title:\"rust lang\"").unwrap(), Query::Term(Term::phrase("rust lang").in_field("title")));
+        assert_eq!(Query::parse("*:*").unwrap(), Query::raw("*:*"));
+        assert_eq!(Query::parse("title:*").unwrap(), Query::Term(Term::exists("title")));
+    }
+
+    #[test]
+    fn parses_escapes_into_unescaped_values() {
+        assert_eq!(Query::parse("a\\:b\\ c").unwrap(), Query::Term(Term::new(TermValue::Text("a:b c".to_owned()))));
+        assert_eq!(Query::parse("\"say \\\"hi\\\"\"").unwrap(), Query::Term(Term::phrase("say \"hi\"")));
+        assert_eq!(Query::parse("c\\+\\+*").unwrap(), Query::Term(Term::prefix("c++")));
+    }
+
+    #[test]
+    fn parses_wildcards_fuzzy_regex_and_slop() {
+        assert_eq!(Query::parse("te?t*").unwrap(), Query::Term(Term::wildcard("te?t*")));
+        assert_eq!(Query::parse("rust~1").unwrap(), Query::Term(Term::fuzzy("rust", 1)));
+        assert_eq!(Query::parse("rust~").unwrap(), Query::Term(Term::fuzzy("rust", 2)));
+        assert_eq!(Query::parse("\"a b\"~3").unwrap(), Query::Term(Term::phrase("a b").slop(3)));
+        assert_eq!(Query::parse("name:/ab+c/").unwrap(), Query::Term(Term::regex("ab+c").in_field("name")));
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(Query::parse("price:[10 TO 20}").unwrap(), Query::Term(Term::range(10..20).in_field("price")));
+        assert_eq!(Query::parse("created:[NOW-1YEARS TO *]").unwrap().to_string(), "created:[NOW-1YEARS TO *]");
+        round_trip("title:{\"a b\" TO c]");
+    }
+
+    #[test]
+    fn parses_boolean_operators_and_modifiers() {
+        let query = Query::parse("+title:rust^2 AND NOT -b || c^=1.5").unwrap();
+        assert_eq!(query, Query::Boolean(vec![
+            BooleanPart::Clause(Query::Term(Term::from_str("rust").in_field("title").required().boost(2.0))),
+            BooleanPart::Operator(Operator::And),
+            BooleanPart::Operator(Operator::Not),
+            BooleanPart::Clause(Query::Term(Term::from_str("b").prohibit())),
+            BooleanPart::Operator(Operator::Or),
+            BooleanPart::Clause(Query::Term(Term::from_str("c").constant_score(1.5)))
+        ]));
+        assert_eq!(query.to_string(), "+title:rust^2 AND NOT -b OR c^=1.5");
+    }
+
+    #[test]
+    fn parses_groups() {
+        let query = Query::parse("a AND -title:(b OR \"c d\")^3").unwrap();
+        let group = Group::new(Query::parse("b OR \"c d\"").unwrap()).in_field("title").prohibit().boost(3.0);
+        assert_eq!(query, Query::from_term(Term::from_str("a")).and().group(group));
+        round_trip("(a (b OR c)) AND d");
+    }
+
+    #[test]
+    fn parses_local_params() {
+        let query = Query::parse("{!lucene q.op=AND df=title}rust solr").unwrap();
+        let local_params = LocalParams::new("lucene").param("q.op", "AND").param("df", "title");
+        assert_eq!(query, Query::from_term(Term::from_str("rust")).term(Term::from_str("solr")).local_params(local_params));
+
+        let query = Query::parse("{!frange l=0 u='1 0'}log(popularity)").unwrap();
+        assert_eq!(query, Query::raw("log(popularity)").local_params(LocalParams::new("frange").param("l", "0").param("u", "1 0")));
+
+        round_trip("{!parent which=$parents v=$children}");
+        round_trip("a AND ({!term f=id v=1})");
+        assert_eq!(Query::parse("a AND {!term f=id}1").unwrap().to_string(), "a AND {!term f=id v=1}");
+    }
+
+    #[test]
+    fn parsed_queries_can_be_rewritten() {
+        let query = Query::parse("title:rust").unwrap();
+        let group = BooleanGroup::new().must(query).filter(Query::parse("type:book").unwrap());
+        assert_eq!(Query::from(group).to_string(), "+title:rust +filter(type:book)");
+    }
+
+    #[test]
+    fn errors_tell_the_position() {
+        assert_eq!(error_position(""), 0);
+        assert_eq!(error_position("a AND"), 5);
+        assert_eq!(error_position("a OR OR b"), 5);
+        assert_eq!(error_position("(a b"), 4);
+        assert_eq!(error_position("a b)"), 3);
+        assert_eq!(error_position("x:\"open"), 2);
+        assert_eq!(error_position("[1 2]"), 3);
+        assert_eq!(error_position("n:[1 TO 2"), 2);
+        assert_eq!(error_position("a^x"), 1);
+        assert_eq!(error_position("\"a\"~x"), 3);
+        assert_eq!(error_position("a*~2"), 2);
+        assert_eq!(error_position("{!lucene q.op}a"), 13);
+        assert_eq!(error_position("{!lucene a=b"), 0);
+        assert_eq!(error_position("title:"), 6);
+        assert_eq!(error_position("a\\"), 1);
+    }
+
+    #[test]
+    fn error_message_includes_position() {
+        let error = Query::parse("a AND").expect_err("dangling operator");
+        assert_eq!(error.to_string(), "Invalid query at position 5: expected a clause after `AND`");
+    }
+}
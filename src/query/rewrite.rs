@@ -0,0 +1,380 @@
+//! Visiting and rewriting query trees.
+//!
+//! Fold rebuilds a query node by node, its methods return none to drop the node. Visit walks
+//! a query without changing it. Both default to descending into every node, override the methods
+//! of the nodes to handle.
+//!
+//! ```rust
+//! use rsolr::query::{BooleanGroup, Query, Term};
+//! use rsolr::query::rewrite::{FieldAllowList, FieldRenamer, TermNormalizer};
+//!
+//! let query = Query::parse("title:Rust AND secret:x AND author:(Smith OR Doe)").expect("invalid query");
+//! let query = query
+//!     .fold(&mut FieldAllowList::new(&["title", "author"]))
+//!     .and_then(|query| query.fold(&mut FieldRenamer::new().rename("title", "title_t")))
+//!     .and_then(|query| query.fold(&mut TermNormalizer::lowercase()))
+//!     .expect("every clause was dropped");
+//! assert_eq!(query.to_string(), "title_t:rust AND author:(smith OR doe)");
+//!
+//! let tenant = BooleanGroup::new().must(query).filter(Term::from_str("acme").in_field("tenant"));
+//! assert_eq!(Query::from(tenant).to_string(), "+(title_t:rust AND author:(smith OR doe)) +filter(tenant:acme)");
+//! ```
+//!
+//! Custom rewrites implement Fold, i.e. expanding synonyms.
+//!
+//! ```rust
+//! use rsolr::query::{Query, Term, TermValue};
+//! use rsolr::query::rewrite::Fold;
+//!
+//! struct Synonyms;
+//!
+//! impl Fold for Synonyms {
+//!     fn fold_term(&mut self, term: Term) -> Option<Query> {
+//!         match &term.value {
+//!             TermValue::Text(text) if text == "tv" => {
+//!                 let television = Term { value: TermValue::Text("television".to_owned()), ..term.clone() };
+//!                 Some(Query::from_term(term).or().term(television).boost(1.0))
+//!             },
+//!             _ => Some(Query::Term(term))
+//!         }
+//!     }
+//! }
+//!
+//! let query = Query::parse("cheap tv").unwrap().fold(&mut Synonyms).unwrap();
+//! assert_eq!(query.to_string(), "cheap (tv OR television)^1");
+//! ```
+
+use std::collections::HashMap;
+use super::{BooleanGroup, BooleanPart, Group, LocalParams, Operator, Query, Term, TermValue};
+
+/// Rewrites a query, returning none drops the node.
+pub trait Fold {
+    fn fold_query(&mut self, query: Query) -> Option<Query> {
+        fold_query(self, query)
+    }
+
+    fn fold_term(&mut self, term: Term) -> Option<Query> {
+        Some(Query::Term(term))
+    }
+
+    fn fold_group(&mut self, group: Group) -> Option<Query> {
+        fold_group(self, group)
+    }
+
+    fn fold_boolean_group(&mut self, group: BooleanGroup) -> Option<Query> {
+        fold_boolean_group(self, group)
+    }
+
+    fn fold_boolean(&mut self, parts: Vec<BooleanPart>) -> Option<Query> {
+        fold_boolean(self, parts)
+    }
+
+    fn fold_local_params(&mut self, local_params: LocalParams, query: Query) -> Option<Query> {
+        fold_local_params(self, local_params, query)
+    }
+}
+
+/// Dispatches to the Fold method of the node.
+pub fn fold_query<F: Fold + ?Sized>(folder: &mut F, query: Query) -> Option<Query> {
+    match query {
+        Query::Term(term) => folder.fold_term(term),
+        Query::Group(group) => folder.fold_group(group),
+        Query::BooleanGroup(group) => folder.fold_boolean_group(group),
+        Query::Boolean(parts) => folder.fold_boolean(parts),
+        Query::LocalParams(local_params, query) => folder.fold_local_params(local_params, *query)
+    }
+}
+
+/// Folds the grouped query, the group is dropped with it.
+pub fn fold_group<F: Fold + ?Sized>(folder: &mut F, group: Group) -> Option<Query> {
+    let query = folder.fold_query(*group.query)?;
+    Some(Query::Group(Group { query: Box::new(query), ..group }))
+}
+
+/// Folds every clause list, the group is dropped if no clause is left.
+pub fn fold_boolean_group<F: Fold + ?Sized>(folder: &mut F, group: BooleanGroup) -> Option<Query> {
+    let mut fold_all = |clauses: Vec<Query>| -> Vec<Query> {
+        clauses.into_iter().filter_map(|clause| folder.fold_query(clause)).collect()
+    };
+    let group = BooleanGroup {
+        must: fold_all(group.must),
+        should: fold_all(group.should),
+        must_not: fold_all(group.must_not),
+        filter: fold_all(group.filter),
+        ..group
+    };
+    match group.must.is_empty() && group.should.is_empty() && group.must_not.is_empty() && group.filter.is_empty() {
+        true => None,
+        false => Some(Query::BooleanGroup(group))
+    }
+}
+
+/// Folds the clauses, a dropped clause takes the operators before it, or the one after it when it was the first.
+pub fn fold_boolean<F: Fold + ?Sized>(folder: &mut F, parts: Vec<BooleanPart>) -> Option<Query> {
+    let mut kept: Vec<BooleanPart> = vec![];
+    let mut operators: Vec<Operator> = vec![];
+    for part in parts {
+        match part {
+            BooleanPart::Operator(operator) => operators.push(operator),
+            BooleanPart::Clause(clause) => {
+                if let Some(clause) = folder.fold_query(clause) {
+                    if kept.is_empty() {
+                        operators.retain(|operator| *operator == Operator::Not);
+                    }
+                    kept.extend(operators.drain(..).map(BooleanPart::Operator));
+                    kept.push(BooleanPart::Clause(clause));
+                }
+                operators.clear();
+            }
+        }
+    }
+    match kept.len() {
+        0 => None,
+        1 => match kept.pop() {
+            Some(BooleanPart::Clause(clause)) => Some(clause),
+            _ => unreachable!("a single part is a clause")
+        },
+        _ => Some(Query::Boolean(kept))
+    }
+}
+
+/// Folds the query of the local params, they are dropped with it.
+pub fn fold_local_params<F: Fold + ?Sized>(folder: &mut F, local_params: LocalParams, query: Query) -> Option<Query> {
+    let query = folder.fold_query(query)?;
+    Some(query.local_params(local_params))
+}
+
+/// Walks a query without changing it.
+pub trait Visit {
+    fn visit_query(&mut self, query: &Query) {
+        visit_query(self, query)
+    }
+
+    fn visit_term(&mut self, _term: &Term) {}
+
+    fn visit_group(&mut self, group: &Group) {
+        self.visit_query(&group.query)
+    }
+
+    fn visit_boolean_group(&mut self, group: &BooleanGroup) {
+        for clause in group.must.iter().chain(&group.should).chain(&group.must_not).chain(&group.filter) {
+            self.visit_query(clause);
+        }
+    }
+
+    fn visit_local_params(&mut self, _local_params: &LocalParams, query: &Query) {
+        self.visit_query(query)
+    }
+}
+
+/// Dispatches to the Visit method of the node.
+pub fn visit_query<V: Visit + ?Sized>(visitor: &mut V, query: &Query) {
+    match query {
+        Query::Term(term) => visitor.visit_term(term),
+        Query::Group(group) => visitor.visit_group(group),
+        Query::BooleanGroup(group) => visitor.visit_boolean_group(group),
+        Query::Boolean(parts) => {
+            for part in parts {
+                if let BooleanPart::Clause(clause) = part {
+                    visitor.visit_query(clause);
+                }
+            }
+        },
+        Query::LocalParams(local_params, query) => visitor.visit_local_params(local_params, query)
+    }
+}
+
+/// Maps field names, i.e. public names to the ones in the schema.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldRenamer {
+    fields: HashMap<String, String>
+}
+
+impl FieldRenamer {
+
+    pub fn new() -> Self {
+        FieldRenamer::default()
+    }
+
+    pub fn rename(mut self, from: &str, to: &str) -> Self {
+        self.fields.insert(from.to_owned(), to.to_owned());
+        self
+    }
+
+    fn renamed(&self, field: Option<String>) -> Option<String> {
+        field.map(|field| self.fields.get(&field).cloned().unwrap_or(field))
+    }
+}
+
+impl Fold for FieldRenamer {
+    fn fold_term(&mut self, term: Term) -> Option<Query> {
+        Some(Query::Term(Term { field: self.renamed(term.field), ..term }))
+    }
+
+    fn fold_group(&mut self, group: Group) -> Option<Query> {
+        let group = Group { field: self.renamed(group.field), ..group };
+        fold_group(self, group)
+    }
+}
+
+/// Drops the clauses in fields not allowed and, unless allowed, the ones with local params.
+/// Clauses without field search the default field, they are kept.
+/// Dropping a required clause widens the results, validate instead where that matters.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldAllowList {
+    fields: Vec<String>,
+    local_params: bool
+}
+
+impl FieldAllowList {
+
+    pub fn new(fields: &[&str]) -> Self {
+        FieldAllowList { fields: fields.iter().map(|field| field.to_string()).collect(), local_params: false }
+    }
+
+    /// Keeps local params, they may switch parser or search any field.
+    pub fn allow_local_params(mut self) -> Self {
+        self.local_params = true;
+        self
+    }
+
+    fn allows(&self, field: &Option<String>) -> bool {
+        match field {
+            Some(field) => self.fields.contains(field),
+            None => true
+        }
+    }
+}
+
+impl Fold for FieldAllowList {
+    fn fold_term(&mut self, term: Term) -> Option<Query> {
+        match self.allows(&term.field) {
+            true => Some(Query::Term(term)),
+            false => None
+        }
+    }
+
+    fn fold_group(&mut self, group: Group) -> Option<Query> {
+        match self.allows(&group.field) {
+            true => fold_group(self, group),
+            false => None
+        }
+    }
+
+    fn fold_local_params(&mut self, local_params: LocalParams, query: Query) -> Option<Query> {
+        match self.local_params {
+            true => fold_local_params(self, local_params, query),
+            false => None
+        }
+    }
+}
+
+/// Applies a function to the text of terms, phrases, wildcards, prefixes and fuzzy terms.
+pub struct TermNormalizer<N: FnMut(&str) -> String> {
+    normalize: N
+}
+
+impl<N: FnMut(&str) -> String> TermNormalizer<N> {
+
+    pub fn new(normalize: N) -> Self {
+        TermNormalizer { normalize }
+    }
+}
+
+impl TermNormalizer<fn(&str) -> String> {
+
+    /// Lowercases the text, i.e. for wildcards which skip the analysis.
+    pub fn lowercase() -> Self {
+        TermNormalizer::new(str::to_lowercase)
+    }
+}
+
+impl<N: FnMut(&str) -> String> Fold for TermNormalizer<N> {
+    fn fold_term(&mut self, term: Term) -> Option<Query> {
+        let normalize = &mut self.normalize;
+        let value = match term.value {
+            TermValue::Text(text) => TermValue::Text(normalize(&text)),
+            TermValue::Phrase { phrase, slop } => TermValue::Phrase { phrase: normalize(&phrase), slop },
+            TermValue::Wildcard(pattern) => TermValue::Wildcard(normalize(&pattern)),
+            TermValue::Prefix(prefix) => TermValue::Prefix(normalize(&prefix)),
+            TermValue::Fuzzy { text, distance } => TermValue::Fuzzy { text: normalize(&text), distance },
+            value => value
+        };
+        Some(Query::Term(Term { value, ..term }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> Query {
+        Query::parse(query).expect(query)
+    }
+
+    #[test]
+    fn default_fold_keeps_the_query() {
+        let query = parse("+a:b^2 AND NOT (c OR {!term f=id v=1}) d:[1 TO *]");
+        assert_eq!(query.clone().fold(&mut FieldRenamer::new()), Some(query));
+    }
+
+    #[test]
+    fn renamer_maps_term_and_group_fields() {
+        let mut renamer = FieldRenamer::new().rename("title", "title_t").rename("author", "author_s");
+        let query = parse("title:rust AND author:(a OR title:b) AND other:c");
+        assert_eq!(query.fold(&mut renamer).unwrap().to_string(), "title_t:rust AND author_s:(a OR title_t:b) AND other:c");
+    }
+
+    #[test]
+    fn allow_list_drops_clauses_with_their_operators() {
+        let mut allow_list = FieldAllowList::new(&["title"]);
+        assert_eq!(parse("title:a AND secret:b OR c").fold(&mut allow_list).unwrap().to_string(), "title:a OR c");
+        assert_eq!(parse("secret:b AND NOT title:a").fold(&mut allow_list).unwrap().to_string(), "NOT title:a");
+        assert_eq!(parse("secret:(a b) title:c").fold(&mut allow_list).unwrap().to_string(), "title:c");
+        assert_eq!(parse("secret:a OR secret:b").fold(&mut allow_list), None);
+    }
+
+    #[test]
+    fn allow_list_drops_local_params_unless_allowed() {
+        let query = parse("a OR {!join from=id to=ref}secret:x");
+        assert_eq!(query.clone().fold(&mut FieldAllowList::new(&[])).unwrap().to_string(), "a");
+        let kept = query.fold(&mut FieldAllowList::new(&[]).allow_local_params()).unwrap();
+        assert_eq!(kept.to_string(), "a OR {!join from=id to=ref v=secret:x}");
+    }
+
+    #[test]
+    fn allow_list_empties_boolean_groups() {
+        let group = BooleanGroup::new().must(Term::from_str("a").in_field("secret")).filter(Term::from_str("b").in_field("title"));
+        let folded = Query::from(group).fold(&mut FieldAllowList::new(&["title"])).unwrap();
+        assert_eq!(folded.to_string(), "+filter(title:b)");
+
+        let group = BooleanGroup::new().must(Term::from_str("a").in_field("secret"));
+        assert_eq!(Query::from(group).fold(&mut FieldAllowList::new(&["title"])), None);
+    }
+
+    #[test]
+    fn normalizer_changes_text_values_only() {
+        let mut normalizer = TermNormalizer::lowercase();
+        let query = parse("Title:Rust \"Big Data\"~2 Te?t* Pre* Fuzz~1 n:[A TO B] /R.*/");
+        assert_eq!(query.fold(&mut normalizer).unwrap().to_string(), "Title:rust \"big data\"~2 te?t* pre* fuzz~1 n:[A TO B] /R.*/");
+
+        let mut trimmer = TermNormalizer::new(|text: &str| text.trim_end_matches('s').to_owned());
+        assert_eq!(parse("books").fold(&mut trimmer).unwrap().to_string(), "book");
+    }
+
+    #[test]
+    fn visitor_walks_every_term() {
+        struct Fields(Vec<String>);
+
+        impl Visit for Fields {
+            fn visit_term(&mut self, term: &Term) {
+                self.0.push(term.field.clone().unwrap_or_default());
+            }
+        }
+
+        let mut fields = Fields(vec![]);
+        let group = BooleanGroup::new().must(parse("a:1 OR (b:2 c:3)")).must_not(Term::from_str("x").in_field("d"));
+        Query::from(group).visit(&mut fields);
+        assert_eq!(fields.0, vec!["a", "b", "c", "d"]);
+    }
+}
@@ -0,0 +1,288 @@
+//! Turning end-user search input into safe queries.
+//!
+//! Whatever the input, the sanitized query is valid syntax: special characters are escaped,
+//! quotes are balanced, and only allowed fields can be searched.
+//!
+//! ```rust
+//! use rsolr::Client;
+//! use rsolr::query::sanitize::{Operators, Sanitizer};
+//!
+//! fn search(user_input: &str) {
+//!     let sanitizer = Sanitizer::edismax().allow_fields(&["title", "author"]).operators(Operators::Allow);
+//!     if let Some(query) = sanitizer.sanitize(user_input) {
+//!         Client::new("http://solr:8983", "collection")
+//!             .select(query)
+//!             .add_query_param("qf", "title body")
+//!             .run().expect("request failed.");
+//!     }
+//! }
+//!
+//! let sanitizer = Sanitizer::lucene().allow_fields(&["title"]);
+//! let query = sanitizer.sanitize("title:rust secret:x AND \"c++ (lang)").unwrap();
+//! assert_eq!(query.to_string(), "title:rust secret\\:x \"AND\" \"c++ (lang)\"");
+//! ```
+
+use super::{BooleanPart, LocalParams, Occur, Operator, Query, Term};
+
+pub const DEFAULT_MAX_LENGTH: usize = 500;
+pub const DEFAULT_MAX_CLAUSES: usize = 20;
+
+/// What happens to `AND`, `OR`, `NOT`, `&&`, `||`, `!` and the `+` and `-` prefixes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operators {
+    /// Searched for as text.
+    Escape,
+    /// Removed from the input.
+    Strip,
+    /// Used as operators, misplaced ones are dropped.
+    Allow
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Target {
+    Lucene,
+    EDisMax
+}
+
+/// Sanitizing policy, by default operators are escaped, no field prefix is allowed,
+/// the input is cut at 500 characters and 20 clauses.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sanitizer {
+    target: Target,
+    operators: Operators,
+    fields: Vec<String>,
+    max_length: usize,
+    max_clauses: usize
+}
+
+impl Sanitizer {
+
+    /// Sanitizes into a standard query parser query.
+    pub fn lucene() -> Self {
+        Sanitizer::new(Target::Lucene)
+    }
+
+    /// Sanitizes into an `{!edismax}` query, its `uf` restricts the fields to the allowed ones.
+    pub fn edismax() -> Self {
+        Sanitizer::new(Target::EDisMax)
+    }
+
+    fn new(target: Target) -> Self {
+        Sanitizer { target, operators: Operators::Escape, fields: vec![], max_length: DEFAULT_MAX_LENGTH, max_clauses: DEFAULT_MAX_CLAUSES }
+    }
+
+    pub fn operators(mut self, operators: Operators) -> Self {
+        self.operators = operators;
+        self
+    }
+
+    /// Fields users may prefix their terms with, i.e. `title:rust`, other prefixes are searched as text.
+    pub fn allow_fields(mut self, fields: &[&str]) -> Self {
+        self.fields = fields.iter().map(|field| field.to_string()).collect();
+        self
+    }
+
+    /// Characters of the input kept, the rest is cut.
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Clauses kept, the rest is cut.
+    pub fn max_clauses(mut self, max_clauses: usize) -> Self {
+        self.max_clauses = max_clauses;
+        self
+    }
+
+    /// The safe query, none if nothing is left to search for.
+    pub fn sanitize(&self, input: &str) -> Option<Query> {
+        let input: String = input.chars().take(self.max_length).collect();
+        let mut parts: Vec<BooleanPart> = vec![];
+        let mut pending: Vec<Operator> = vec![];
+        let mut clauses = 0;
+
+        for token in tokenize(&input) {
+            if clauses == self.max_clauses {
+                break;
+            }
+            let (field, occur, value) = match token {
+                Token::Word(word) => match (self.operators, operator(word)) {
+                    (Operators::Allow, Some(operator)) => {
+                        pending.push(operator);
+                        continue;
+                    },
+                    (Operators::Strip, Some(_)) => continue,
+                    _ => {
+                        let (occur, word) = self.occur(word);
+                        let (field, word) = self.field(word);
+                        match word.is_empty() {
+                            true => continue,
+                            false => (field, occur, Term::from_str(word))
+                        }
+                    }
+                },
+                Token::Phrase(field, phrase) => {
+                    let (occur, field) = self.occur(field);
+                    let (field, rest) = self.field(field);
+                    match rest.is_empty() && (field.is_some() || field_prefix(rest).is_none()) {
+                        true => (field, occur, Term::phrase(&phrase)),
+                        false => (None, occur, Term::phrase(&format!("{}{}", rest, phrase)))
+                    }
+                }
+            };
+
+            if !parts.is_empty() {
+                if let Some(operator) = pending.iter().rev().find(|operator| **operator != Operator::Not) {
+                    parts.push(BooleanPart::Operator(*operator));
+                }
+            }
+            if pending.contains(&Operator::Not) {
+                parts.push(BooleanPart::Operator(Operator::Not));
+            }
+            pending.clear();
+
+            let mut term = value;
+            term.field = field;
+            term.modifiers.occur = occur;
+            parts.push(BooleanPart::Clause(Query::Term(term)));
+            clauses += 1;
+        }
+
+        let query = match parts.len() {
+            0 => return None,
+            1 => match parts.pop() {
+                Some(BooleanPart::Clause(query)) => query,
+                _ => unreachable!("a single part is a clause")
+            },
+            _ => Query::Boolean(parts)
+        };
+        Some(match self.target {
+            Target::Lucene => query,
+            Target::EDisMax => {
+                let user_fields = match self.fields.is_empty() {
+                    true => "-*".to_owned(),
+                    false => self.fields.join(" ")
+                };
+                query.local_params(LocalParams::new("edismax").param("uf", &user_fields))
+            }
+        })
+    }
+
+    /// Splits off the `+` or `-` prefix if operators are allowed, strips it if they are stripped.
+    fn occur<'a>(&self, word: &'a str) -> (Option<Occur>, &'a str) {
+        let occur = match word.chars().next() {
+            Some('+') => Occur::Required,
+            Some('-') => Occur::Prohibited,
+            _ => return (None, word)
+        };
+        match self.operators {
+            Operators::Escape => (None, word),
+            Operators::Strip => (None, word.trim_start_matches(['+', '-'])),
+            Operators::Allow => (Some(occur), &word[1..])
+        }
+    }
+
+    /// Splits off an allowed field prefix.
+    fn field<'a>(&self, word: &'a str) -> (Option<String>, &'a str) {
+        match field_prefix(word) {
+            Some((field, rest)) if self.fields.iter().any(|allowed| allowed == field) => (Some(field.to_owned()), rest),
+            _ => (None, word)
+        }
+    }
+}
+
+enum Token<'a> {
+    Word(&'a str),
+    /// A phrase with the text right before its opening quote, i.e. a field prefix.
+    Phrase(&'a str, String)
+}
+
+/// Words separated by whitespace and quoted phrases, an unmatched quote closes at the end.
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut rest = input.trim_start();
+    while !rest.is_empty() {
+        let end = rest.find(|c: char| c.is_whitespace() || c == '"').unwrap_or(rest.len());
+        let (word, after) = rest.split_at(end);
+        match after.strip_prefix('"') {
+            Some(quoted) => {
+                let (phrase, after) = quoted.split_once('"').unwrap_or((quoted, ""));
+                if !phrase.trim().is_empty() {
+                    tokens.push(Token::Phrase(word, phrase.trim().to_owned()));
+                } else if !word.is_empty() {
+                    tokens.push(Token::Word(word));
+                }
+                rest = after.trim_start();
+            },
+            None => {
+                tokens.push(Token::Word(word));
+                rest = after.trim_start();
+            }
+        }
+    }
+    tokens
+}
+
+fn operator(word: &str) -> Option<Operator> {
+    match word {
+        "AND" | "&&" => Some(Operator::And),
+        "OR" | "||" => Some(Operator::Or),
+        "NOT" | "!" => Some(Operator::Not),
+        _ => None
+    }
+}
+
+fn field_prefix(word: &str) -> Option<(&str, &str)> {
+    word.split_once(':').filter(|(field, _)| !field.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sanitized(sanitizer: &Sanitizer, input: &str) -> String {
+        sanitizer.sanitize(input).map(|query| query.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn escapes_special_characters() {
+        let sanitizer = Sanitizer::lucene();
+        assert_eq!(sanitized(&sanitizer, "c++ (lang) a:b {!x} \\"), "c\\+\\+ \\(lang\\) a\\:b \\{\\!x\\} \\\\");
+        assert_eq!(sanitized(&sanitizer, "   "), "");
+    }
+
+    #[test]
+    fn balances_quotes() {
+        let sanitizer = Sanitizer::lucene();
+        assert_eq!(sanitized(&sanitizer, "a \"big data\" \"open end"), "a \"big data\" \"open end\"");
+        assert_eq!(sanitized(&sanitizer, "say\"\" \"\""), "say");
+    }
+
+    #[test]
+    fn operator_policies() {
+        let input = "+rust AND -java OR NOT c# AND";
+        assert_eq!(sanitized(&Sanitizer::lucene(), input), "\\+rust \"AND\" \\-java \"OR\" \"NOT\" c# \"AND\"");
+        assert_eq!(sanitized(&Sanitizer::lucene().operators(Operators::Strip), input), "rust java c#");
+        assert_eq!(sanitized(&Sanitizer::lucene().operators(Operators::Allow), input), "+rust AND -java OR NOT c#");
+        assert_eq!(sanitized(&Sanitizer::lucene().operators(Operators::Allow), "OR AND a OR AND b NOT"), "a AND b");
+    }
+
+    #[test]
+    fn allows_listed_field_prefixes_only() {
+        let sanitizer = Sanitizer::lucene().allow_fields(&["title"]).operators(Operators::Allow);
+        assert_eq!(sanitized(&sanitizer, "title:rust -title:\"big data\" secret:x 12:30"), "title:rust -title:\"big data\" secret\\:x 12\\:30");
+        assert_eq!(sanitized(&sanitizer, "secret:\"a b\" title:"), "\"secret:a b\"");
+    }
+
+    #[test]
+    fn caps_length_and_clauses() {
+        assert_eq!(sanitized(&Sanitizer::lucene().max_length(7), "rust solr lucene"), "rust so");
+        assert_eq!(sanitized(&Sanitizer::lucene().operators(Operators::Allow).max_clauses(2), "a AND b AND c"), "a AND b");
+    }
+
+    #[test]
+    fn edismax_restricts_user_fields() {
+        assert_eq!(sanitized(&Sanitizer::edismax(), "rust"), "{!edismax uf=-*}rust");
+        assert_eq!(sanitized(&Sanitizer::edismax().allow_fields(&["title", "body"]), "title:rust"), "{!edismax uf='title body'}title:rust");
+    }
+}
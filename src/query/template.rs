@@ -0,0 +1,234 @@
+//! Query templates with named placeholders, bound like prepared statements.
+//!
+//! A template is parsed and validated once, `$name` placeholders are filled per request with values
+//! escaped for their place: a term, a phrase or a range bound. `$` in local params stays a parameter reference.
+//! Placeholders cannot stand for field names, the template fixes the searched fields.
+//!
+//! ```rust
+//! use rsolr::query::template::QueryTemplate;
+//!
+//! let template = QueryTemplate::parse("category:$cat AND price:[$min TO *] AND title:\"$title\"").unwrap();
+//! let query = template.bindings()
+//!     .bind("cat", "home & garden")
+//!     .bind("min", 10)
+//!     .bind("title", "the \"best\" chair")
+//!     .query().unwrap();
+//! assert_eq!(query.to_string(), "category:\"home & garden\" AND price:[10 TO *] AND title:\"the \\\"best\\\" chair\"");
+//!
+//! assert!(template.bindings().bind("cat", "books").query().is_err()); // $min and $title are missing
+//! ```
+
+use std::collections::HashMap;
+use crate::error::RSolrError;
+use super::{escape_phrase, escape_query_chars, Query, RangeValue};
+
+/// Where a placeholder stands, deciding how its value is escaped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Context {
+    Term,
+    Phrase,
+    Range
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Literal(String),
+    Placeholder(String, Context)
+}
+
+/// A validated query with `$name` placeholders.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryTemplate {
+    segments: Vec<Segment>
+}
+
+impl QueryTemplate {
+
+    /// Parses the template, it is invalid if the query is invalid with its placeholders filled or a
+    /// placeholder stands for a field name.
+    pub fn parse(template: &str) -> Result<Self, RSolrError> {
+        let template = QueryTemplate { segments: segments(template)? };
+        Query::parse(&template.render(|name, _| "x".repeat(name.len() + 1)))?;
+        Ok(template)
+    }
+
+    /// The placeholder names, each once in order of appearance.
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = vec![];
+        for segment in &self.segments {
+            if let Segment::Placeholder(name, _) = segment {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    pub fn bindings(&self) -> Bindings<'_> {
+        Bindings { template: self, values: HashMap::new() }
+    }
+
+    fn render<F: Fn(&str, Context) -> String>(&self, value: F) -> String {
+        self.segments.iter().map(|segment| match segment {
+            Segment::Literal(literal) => literal.clone(),
+            Segment::Placeholder(name, context) => value(name, *context)
+        }).collect()
+    }
+}
+
+/// Values bound to the placeholders of a template.
+#[derive(Clone, Debug)]
+pub struct Bindings<'a> {
+    template: &'a QueryTemplate,
+    values: HashMap<String, String>
+}
+
+impl Bindings<'_> {
+
+    /// Binds the value of `$name`, formatted the way range bounds are.
+    pub fn bind<V: RangeValue>(mut self, name: &str, value: V) -> Self {
+        self.values.insert(name.to_owned(), value.to_range_value());
+        self
+    }
+
+    /// The filled query, missing and unknown bindings are errors.
+    pub fn query(&self) -> Result<Query, RSolrError> {
+        let placeholders = self.template.placeholders();
+        let missing: Vec<&str> = placeholders.iter().copied().filter(|name| !self.values.contains_key(*name)).collect();
+        if !missing.is_empty() {
+            return Err(RSolrError::Validation(format!("missing binding for `${}`", missing.join("`, `$"))));
+        }
+        let mut unknown: Vec<&str> = self.values.keys().map(String::as_str).filter(|name| !placeholders.contains(name)).collect();
+        if !unknown.is_empty() {
+            unknown.sort();
+            return Err(RSolrError::Validation(format!("no placeholder for binding `{}`", unknown.join("`, `"))));
+        }
+        Query::parse(&self.template.render(|name, context| escape(&self.values[name], context)))
+    }
+}
+
+/// Escapes a range value for its place, quoted values are phrases already.
+fn escape(value: &str, context: Context) -> String {
+    let quoted = value.len() > 1 && value.starts_with('"') && value.ends_with('"');
+    match (context, quoted) {
+        (Context::Range, _) => value.to_owned(),
+        (Context::Term, true) => value.to_owned(),
+        (Context::Term, false) => escape_query_chars(value),
+        (Context::Phrase, true) => value[1..value.len() - 1].to_owned(),
+        (Context::Phrase, false) => escape_phrase(value)
+    }
+}
+
+/// Splits the template at its placeholders, tracking phrases, ranges and local params.
+fn segments(template: &str) -> Result<Vec<Segment>, RSolrError> {
+    let chars: Vec<char> = template.chars().collect();
+    let mut segments = vec![];
+    let mut literal = String::new();
+    let mut phrase = false;
+    let mut range = false;
+    let mut local_params = false;
+    let mut position = 0;
+    while position < chars.len() {
+        let char = chars[position];
+        match char {
+            '\\' => {
+                literal.extend(chars.get(position..position + 2).unwrap_or(&chars[position..]));
+                position += 2;
+                continue;
+            },
+            '"' if !local_params => phrase = !phrase,
+            '{' if !phrase && chars.get(position + 1) == Some(&'!') => local_params = true,
+            '}' if local_params => local_params = false,
+            '[' | '{' if !phrase && !local_params => range = true,
+            ']' | '}' if !phrase && !local_params => range = false,
+            '$' if !local_params => {
+                let name: String = chars[position + 1..].iter()
+                    .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                    .collect();
+                if name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                    if !phrase && chars.get(position + name.len() + 1) == Some(&':') {
+                        let message = format!("placeholder `${}` cannot be a field name", name);
+                        return Err(RSolrError::QueryParse { message, position });
+                    }
+                    let context = match (phrase, range) {
+                        (true, _) => Context::Phrase,
+                        (false, true) => Context::Range,
+                        (false, false) => Context::Term
+                    };
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    position += name.len() + 1;
+                    segments.push(Segment::Placeholder(name, context));
+                    continue;
+                }
+            },
+            _ => {}
+        }
+        literal.push(char);
+        position += 1;
+    }
+    segments.push(Segment::Literal(literal));
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::Date;
+
+    #[test]
+    fn escapes_values_for_their_place() {
+        let template = QueryTemplate::parse("a:$v AND b:\"x $v\" AND c:{$v TO $v]").unwrap();
+        let query = template.bindings().bind("v", "a b:\"c").query().unwrap();
+        assert_eq!(query.to_string(), "a:\"a b:\\\"c\" AND b:\"x a b:\\\"c\" AND c:{\"a b:\\\"c\" TO \"a b:\\\"c\"]");
+        let query = template.bindings().bind("v", "-1:x").query().unwrap();
+        assert_eq!(query.to_string(), "a:\\-1\\:x AND b:\"x -1:x\" AND c:{-1:x TO -1:x]");
+    }
+
+    #[test]
+    fn binds_typed_values() {
+        let template = QueryTemplate::parse("price:[$min TO $max] AND date:[$from TO NOW]").unwrap();
+        let query = template.bindings()
+            .bind("min", 1.5)
+            .bind("max", f64::INFINITY)
+            .bind("from", Date::now().minus(Date::day(7)))
+            .query().unwrap();
+        assert_eq!(query.to_string(), "price:[1.5 TO *] AND date:[NOW-7DAYS TO NOW]");
+    }
+
+    #[test]
+    fn keeps_local_params_references_and_escaped_dollars() {
+        let template = QueryTemplate::parse("{!dismax qf=$qf}$q \\$5").unwrap();
+        assert_eq!(template.placeholders(), vec!["q"]);
+        let query = template.bindings().bind("q", "cheap").query().unwrap();
+        assert_eq!(query.to_string(), "{!dismax qf=$qf}cheap \\$5");
+    }
+
+    #[test]
+    fn placeholders_in_order_without_duplicates() {
+        let template = QueryTemplate::parse("$b x:$a $b_2 $b $1").unwrap();
+        assert_eq!(template.placeholders(), vec!["b", "a", "b_2"]);
+    }
+
+    #[test]
+    fn reports_missing_and_unknown_bindings() {
+        let template = QueryTemplate::parse("a:$x AND b:$y").unwrap();
+        assert!(matches!(template.bindings().query(), Err(RSolrError::Validation(message)) if message == "missing binding for `$x`, `$y`"));
+        let unknown = template.bindings().bind("x", 1).bind("y", 2).bind("z", 3).query();
+        assert!(matches!(unknown, Err(RSolrError::Validation(message)) if message == "no placeholder for binding `z`"));
+    }
+
+    #[test]
+    fn invalid_template_is_an_error() {
+        assert!(matches!(QueryTemplate::parse("a:$x AND"), Err(RSolrError::QueryParse { .. })));
+        assert!(matches!(QueryTemplate::parse("a:($x"), Err(RSolrError::QueryParse { .. })));
+    }
+
+    #[test]
+    fn placeholder_as_field_name_is_an_error() {
+        let field = QueryTemplate::parse("$f:x AND y:$v");
+        assert!(matches!(field, Err(RSolrError::QueryParse { message, position: 0 }) if message == "placeholder `$f` cannot be a field name"));
+        assert!(matches!(QueryTemplate::parse("a:x OR ($g:y)"), Err(RSolrError::QueryParse { position: 8, .. })));
+        assert!(QueryTemplate::parse("a:\"$p: b\"").is_ok());
+    }
+}
@@ -1,6 +1,6 @@
 
-use serde::{Deserialize};
-use serde_json::{json, Value};
+use serde::{Deserialize, Deserializer};
+use serde_json::{json, Map, Value};
 use crate::facet_fields::FacetFields;
 
 /// The response part of the server response body.
@@ -13,6 +13,35 @@ pub struct Response<T> {
     pub docs: Vec<T>
 }
 
+/// Document with its relevance score, request it with FieldList::score.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Scored<T> {
+    pub score: f32,
+
+    /// The document without the score.
+    #[serde(flatten)]
+    pub document: T
+}
+
+/// Document with the pseudo-fields (functions, transformers, aliases) not declared in the document type.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct WithPseudoFields<T> {
+    #[serde(flatten)]
+    pub document: T,
+
+    /// Container for the fields the document type does not consume.
+    #[serde(flatten)]
+    pub pseudo_fields: Map<String, Value>
+}
+
+impl<T> WithPseudoFields<T> {
+
+    /// Deserializes a pseudo-field, None if it's missing or has another type.
+    pub fn get<V: for<'de> Deserialize<'de>>(&self, field: &str) -> Option<V> {
+        self.pseudo_fields.get(field).and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+}
+
 /// The facet part of the response. Facet_fields is parsed, see there.
 #[derive(Deserialize, Clone, Debug)]
 pub struct Facet {
@@ -34,18 +63,175 @@ pub struct SolrResponse<T> where T: Clone {
     pub facet_counts: Option<Facet>,
     pub nextCursorMark: Option<String>,
 
+    /// The document of a realtime get by a single id, None if it does not exist.
+    #[serde(default = "empty_doc")]
+    pub doc: Option<T>,
+
     /// Container for remaining fields.
     #[serde(flatten)]
     pub raw: Value
 }
 
+/// The header of the response body.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[allow(non_snake_case)]
+pub struct ResponseHeader {
+    pub status: i32,
+    pub QTime: u32,
+
+    /// Failed documents of updates tolerated by the TolerantUpdateProcessorFactory.
+    #[serde(default)]
+    pub errors: Vec<UpdateError>,
+    pub maxErrors: Option<i32>
+}
+
+/// The update command a tolerated error belongs to.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub enum UpdateErrorType {
+    #[serde(rename = "ADD")]
+    Add,
+    #[serde(rename = "DELID")]
+    DeleteById,
+    #[serde(rename = "DELQ")]
+    DeleteByQuery,
+    #[serde(other)]
+    Other
+}
+
+/// Tolerated update error of a document, the id is the query for delete by query.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct UpdateError {
+    #[serde(rename = "type")]
+    pub error_type: UpdateErrorType,
+    pub id: String,
+    pub message: String
+}
+
+/// Added or deleted document with its version, negative for deletes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UpdatedDocument {
+    pub id: String,
+    pub version: i64
+}
+
+/// The response body of update requests. Adds and deletes are echoed with `versions=true`, see Client::versions.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[allow(non_snake_case)]
+pub struct UpdateResponse {
+    #[serde(default)]
+    pub responseHeader: ResponseHeader,
+    #[serde(default, deserialize_with = "updated_documents")]
+    pub adds: Vec<UpdatedDocument>,
+    #[serde(default, deserialize_with = "updated_documents")]
+    pub deletes: Vec<UpdatedDocument>,
+
+    /// Container for remaining fields.
+    #[serde(flatten)]
+    pub raw: Value
+}
+
+impl UpdateResponse {
+
+    /// The tolerated errors, the documents not updated.
+    pub fn errors(&self) -> &[UpdateError] {
+        &self.responseHeader.errors
+    }
+}
+
+/// Parses the flat id, version, id, version... list of the echoes.
+fn updated_documents<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<UpdatedDocument>, D::Error> {
+    let values = Vec::<Value>::deserialize(deserializer)?;
+    values.chunks(2).map(|pair| match pair {
+        [id, Value::Number(version)] => Ok(UpdatedDocument {
+            id: id.as_str().map(str::to_owned).unwrap_or_else(|| id.to_string()),
+            version: version.as_i64().ok_or_else(|| serde::de::Error::custom(format!("invalid version {}", version)))?
+        }),
+        _ => Err(serde::de::Error::custom("expected id and version pairs"))
+    }).collect()
+}
+
 impl<T> Default for SolrResponse<T> where T: Clone {
     fn default() -> Self {
-        SolrResponse { response: None, facet_counts: None, nextCursorMark: None, raw: json!("{}") }
+        SolrResponse { response: None, facet_counts: None, nextCursorMark: None, doc: None, raw: json!("{}") }
     }
 }
 
 fn empty_response<T>() -> Option<Response<T>> {
     None
 }
-fn empty_facet_counts() -> Option<Facet> { None }
\ No newline at end of file
+fn empty_facet_counts() -> Option<Facet> { None }
+fn empty_doc<T>() -> Option<T> { None }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+    struct Book {
+        id: String
+    }
+
+    #[test]
+    fn scored_splits_score_from_document() {
+        let scored: Scored<Book> = serde_json::from_str(r#"{"id": "1", "score": 1.5}"#).unwrap();
+        assert_eq!(scored, Scored { score: 1.5, document: Book { id: "1".to_owned() } });
+    }
+
+    #[test]
+    fn with_pseudo_fields_keeps_undeclared_fields() {
+        let document: WithPseudoFields<Book> = serde_json::from_str(r#"{"id": "1", "total": 12, "[shard]": "s1"}"#).unwrap();
+        assert_eq!(document.document, Book { id: "1".to_owned() });
+        assert_eq!(document.get::<u32>("total"), Some(12));
+        assert_eq!(document.get::<String>("[shard]"), Some("s1".to_owned()));
+        assert_eq!(document.get::<String>("id"), None);
+    }
+
+    #[test]
+    fn update_response_parses_header() {
+        let response: UpdateResponse = serde_json::from_str(r#"{"responseHeader": {"status": 0, "QTime": 12, "rf": 2}}"#).unwrap();
+        assert_eq!(response.responseHeader, ResponseHeader { status: 0, QTime: 12, errors: vec![], maxErrors: None });
+        assert!(response.adds.is_empty());
+        assert!(response.errors().is_empty());
+    }
+
+    #[test]
+    fn update_response_parses_version_echoes() {
+        let response: UpdateResponse = serde_json::from_str(r#"{
+            "responseHeader": {"status": 0, "QTime": 3},
+            "adds": ["a", 1632740120218042368, 2, 1632740120219090944],
+            "deletes": ["c", -1632740120220139520]
+        }"#).unwrap();
+        assert_eq!(response.adds, vec![
+            UpdatedDocument { id: "a".to_owned(), version: 1632740120218042368 },
+            UpdatedDocument { id: "2".to_owned(), version: 1632740120219090944 }
+        ]);
+        assert_eq!(response.deletes, vec![UpdatedDocument { id: "c".to_owned(), version: -1632740120220139520 }]);
+        assert!(serde_json::from_str::<UpdateResponse>(r#"{"adds": ["a"]}"#).is_err());
+    }
+
+    #[test]
+    fn update_response_parses_tolerated_errors() {
+        let response: UpdateResponse = serde_json::from_str(r#"{"responseHeader": {
+            "errors": [
+                {"type": "ADD", "id": "1", "message": "ERROR: [doc=1] Error adding field 'price'='abc'"},
+                {"type": "DELQ", "id": "bad:(", "message": "org.apache.solr.search.SyntaxError"},
+                {"type": "NEW", "id": "2", "message": "?"}
+            ],
+            "maxErrors": -1, "status": 0, "QTime": 5
+        }}"#).unwrap();
+        assert_eq!(response.responseHeader.maxErrors, Some(-1));
+        let types: Vec<UpdateErrorType> = response.errors().iter().map(|error| error.error_type.clone()).collect();
+        assert_eq!(types, vec![UpdateErrorType::Add, UpdateErrorType::DeleteByQuery, UpdateErrorType::Other]);
+        assert_eq!(response.errors()[1].id, "bad:(");
+    }
+
+    #[test]
+    fn realtime_get_doc_is_optional() {
+        let found: SolrResponse<Book> = serde_json::from_str(r#"{"doc": {"id": "1"}}"#).unwrap();
+        assert_eq!(found.doc, Some(Book { id: "1".to_owned() }));
+        let missing: SolrResponse<Book> = serde_json::from_str(r#"{"doc": null}"#).unwrap();
+        assert_eq!(missing.doc, None);
+        let search: SolrResponse<Book> = serde_json::from_str(r#"{"response": {"numFound": 0, "start": 0, "numFoundExact": true, "docs": []}}"#).unwrap();
+        assert_eq!(search.doc, None);
+    }
+}
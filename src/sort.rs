@@ -0,0 +1,338 @@
+//! Sort builder for the 'sort' parameter.
+//!
+//! ```rust
+//! use rsolr::Client;
+//! use rsolr::sort::{Direction, Sort};
+//!
+//! fn sorted() {
+//!     let sort = Sort::new()
+//!         .score(Direction::Desc)
+//!         .desc("popularity")
+//!         .function("div(price,weight)", Direction::Asc)
+//!         .with_tiebreaker("id");
+//!     Client::new("http://solr:8983", "collection")
+//!         .select("*:*")
+//!         .sort(&sort)
+//!         .cursor()
+//!         .run().expect("request failed.");
+//! }
+//! ```
+//!
+//! User supplied sorts can be parsed and checked against an allow-list of fields, function arguments included.
+//!
+//! ```rust
+//! use rsolr::sort::Sort;
+//!
+//! let sort: Sort = "price asc, score desc, div(popularity,price) desc".parse().expect("invalid sort");
+//! assert!(sort.validate(&["price", "score", "popularity"]).is_ok());
+//! assert!(sort.validate(&["price", "score"]).is_err());
+//! ```
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::error::RSolrError;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Asc,
+    Desc
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Direction::Asc => write!(f, "asc"),
+            Direction::Desc => write!(f, "desc")
+        }
+    }
+}
+
+/// What a sort clause orders by.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SortTarget {
+    Field(String),
+    Function(String),
+    Score,
+    GeoDist { field: String, lat: f64, lon: f64 }
+}
+
+impl Display for SortTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortTarget::Field(field) => write!(f, "{}", field),
+            SortTarget::Function(function) => write!(f, "{}", function),
+            SortTarget::Score => write!(f, "score"),
+            SortTarget::GeoDist { field, lat, lon } => write!(f, "geodist({},{},{})", field, lat, lon)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SortClause {
+    pub target: SortTarget,
+    pub direction: Direction
+}
+
+impl Display for SortClause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.target, self.direction)
+    }
+}
+
+/// Ordered list of sort clauses, a target is sorted by only once.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sort {
+    clauses: Vec<SortClause>
+}
+
+impl Sort {
+
+    pub fn new() -> Self {
+        Sort::default()
+    }
+
+    /// Adds a clause, unless its target is already in the sort.
+    pub fn clause(mut self, target: SortTarget, direction: Direction) -> Self {
+        if !self.clauses.iter().any(|clause| clause.target == target) {
+            self.clauses.push(SortClause { target, direction });
+        }
+        self
+    }
+
+    /// Sorts by field ascending.
+    pub fn asc(self, field: &str) -> Self {
+        self.clause(SortTarget::Field(field.to_owned()), Direction::Asc)
+    }
+
+    /// Sorts by field descending.
+    pub fn desc(self, field: &str) -> Self {
+        self.clause(SortTarget::Field(field.to_owned()), Direction::Desc)
+    }
+
+    /// Sorts by a function query, a function::Function or its string.
+    pub fn function<F: Display>(self, function: F, direction: Direction) -> Self {
+        self.clause(SortTarget::Function(function.to_string()), direction)
+    }
+
+    /// Sorts by relevance score.
+    pub fn score(self, direction: Direction) -> Self {
+        self.clause(SortTarget::Score, direction)
+    }
+
+    /// Sorts by the distance of a spatial field from a point.
+    pub fn geodist(self, field: &str, lat: f64, lon: f64, direction: Direction) -> Self {
+        self.clause(SortTarget::GeoDist { field: field.to_owned(), lat, lon }, direction)
+    }
+
+    /// Appends the uniqueKey ascending if it's not sorted by yet, cursors require it.
+    pub fn with_tiebreaker(self, unique_key: &str) -> Self {
+        self.asc(unique_key)
+    }
+
+    pub fn clauses(&self) -> &Vec<SortClause> {
+        &self.clauses
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    /// Checks every clause against the allowed fields and `score`, including the field of geodist and the
+    /// field arguments of functions. Parameter references in functions are allowed only if listed, i.e. `$pt`.
+    pub fn validate(&self, allowed: &[&str]) -> Result<(), RSolrError> {
+        for clause in &self.clauses {
+            let fields = match &clause.target {
+                SortTarget::Field(field) => vec![field.as_str()],
+                SortTarget::Function(function) => function_fields(function),
+                SortTarget::Score => vec!["score"],
+                SortTarget::GeoDist { field, .. } => vec![field.as_str()]
+            };
+            if let Some(field) = fields.into_iter().find(|field| !allowed.contains(field)) {
+                return Err(RSolrError::Validation(format!("sorting by {} is not allowed", field)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Sort {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let clauses: Vec<String> = self.clauses.iter().map(|clause| clause.to_string()).collect();
+        write!(f, "{}", clauses.join(","))
+    }
+}
+
+impl FromStr for Sort {
+    type Err = RSolrError;
+
+    /// Parses a Solr sort string, i.e. `price asc, div(a,b) desc`.
+    fn from_str(sort: &str) -> Result<Self, Self::Err> {
+        let mut parsed = Sort::new();
+        for clause in split_top_level(sort)? {
+            let clause = clause.trim();
+            let (target, direction) = match clause.rsplit_once(char::is_whitespace) {
+                Some((target, direction)) => (target.trim(), direction),
+                None => return Err(invalid(clause, "direction is missing"))
+            };
+            let direction = match direction.to_ascii_lowercase().as_str() {
+                "asc" => Direction::Asc,
+                "desc" => Direction::Desc,
+                _ => return Err(invalid(clause, "direction must be asc or desc"))
+            };
+            parsed = parsed.clause(parse_target(target).map_err(|reason| invalid(clause, reason))?, direction);
+        }
+        Ok(parsed)
+    }
+}
+
+fn parse_target(target: &str) -> Result<SortTarget, &'static str> {
+    if target == "score" {
+        return Ok(SortTarget::Score);
+    }
+    if target.ends_with(')') && target.contains('(') {
+        if let Some(arguments) = target.strip_prefix("geodist(").and_then(|rest| rest.strip_suffix(')')) {
+            let arguments: Vec<&str> = arguments.split(',').map(str::trim).collect();
+            if let [field, lat, lon] = arguments[..] {
+                if let (Ok(lat), Ok(lon)) = (lat.parse(), lon.parse()) {
+                    return Ok(SortTarget::GeoDist { field: field.to_owned(), lat, lon });
+                }
+            }
+        }
+        return Ok(SortTarget::Function(target.to_owned()));
+    }
+    if target.is_empty() || target.contains(|c: char| c.is_whitespace() || "(),".contains(c)) {
+        return Err("not a field or function");
+    }
+    Ok(SortTarget::Field(target.to_owned()))
+}
+
+/// Splits at commas outside of parentheses and quotes.
+fn split_top_level(sort: &str) -> Result<Vec<&str>, RSolrError> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut start = 0;
+    for (position, char) in sort.char_indices() {
+        match (quote, char) {
+            (Some(q), c) if q == c => quote = None,
+            (Some(_), _) => (),
+            (None, '\'') | (None, '"') => quote = Some(char),
+            (None, '(') => depth += 1,
+            (None, ')') if depth == 0 => return Err(invalid(sort, "unbalanced parentheses")),
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                parts.push(&sort[start..position]);
+                start = position + 1;
+            },
+            _ => ()
+        }
+    }
+    if depth != 0 || quote.is_some() {
+        return Err(invalid(sort, "unbalanced parentheses or quotes"));
+    }
+    parts.push(&sort[start..]);
+    Ok(parts)
+}
+
+/// The arguments of a function and its nested functions which are neither numbers, booleans nor quoted strings.
+fn function_fields(function: &str) -> Vec<&str> {
+    let mut fields = vec![];
+    let mut quote: Option<char> = None;
+    let mut start = 0;
+    for (position, char) in function.char_indices() {
+        match (quote, char) {
+            (Some(q), c) if q == c => {
+                quote = None;
+                start = position + 1;
+            },
+            (Some(_), _) => (),
+            (None, '\'') | (None, '"') => quote = Some(char),
+            (None, '(') => start = position + 1,
+            (None, ',') | (None, ')') => {
+                let argument = function[start..position].trim();
+                let constant = argument.is_empty() || argument == "true" || argument == "false" || argument.parse::<f64>().is_ok();
+                if !constant {
+                    fields.push(argument);
+                }
+                start = position + 1;
+            },
+            _ => ()
+        }
+    }
+    fields
+}
+
+fn invalid(sort: &str, reason: &str) -> RSolrError {
+    RSolrError::Validation(format!("invalid sort `{}`, {}", sort, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_clauses_in_order() {
+        let sort = Sort::new()
+            .desc("popularity")
+            .score(Direction::Desc)
+            .function("div(price,weight)", Direction::Asc)
+            .geodist("store", 45.15, -93.85, Direction::Asc);
+        assert_eq!(sort.to_string(), "popularity desc,score desc,div(price,weight) asc,geodist(store,45.15,-93.85) asc");
+    }
+
+    #[test]
+    fn ignores_target_already_sorted_by() {
+        let sort = Sort::new().desc("price").asc("price").score(Direction::Desc).score(Direction::Asc);
+        assert_eq!(sort.to_string(), "price desc,score desc");
+    }
+
+    #[test]
+    fn tiebreaker_appended_once() {
+        assert_eq!(Sort::new().desc("price").with_tiebreaker("id").to_string(), "price desc,id asc");
+        assert_eq!(Sort::new().desc("id").with_tiebreaker("id").to_string(), "id desc");
+    }
+
+    #[test]
+    fn parses_sort_string() {
+        let sort: Sort = "price ASC, sum(a, b) desc,score desc , geodist(store,1.5,2) asc".parse().unwrap();
+        assert_eq!(sort, Sort::new()
+            .asc("price")
+            .function("sum(a, b)", Direction::Desc)
+            .score(Direction::Desc)
+            .geodist("store", 1.5, 2.0, Direction::Asc));
+    }
+
+    #[test]
+    fn parse_round_trips() {
+        let sort_string = "price asc,sum(a,b) desc,score desc";
+        assert_eq!(sort_string.parse::<Sort>().unwrap().to_string(), sort_string);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_clauses() {
+        assert!("price".parse::<Sort>().is_err());
+        assert!("price up".parse::<Sort>().is_err());
+        assert!("sum(a,b desc".parse::<Sort>().is_err());
+        assert!("price asc,".parse::<Sort>().is_err());
+        assert!(matches!("a b c asc".parse::<Sort>(), Err(RSolrError::Validation(..))));
+    }
+
+    #[test]
+    fn validate_checks_allow_list() {
+        let sort: Sort = "price asc, score desc, geodist(store,1,2) asc".parse().unwrap();
+        assert!(sort.validate(&["price", "score", "store"]).is_ok());
+        let error = sort.validate(&["price", "score", "geodist"]).expect_err("store is not allowed");
+        assert_eq!(error.to_string(), "Invalid request parameter: `sorting by store is not allowed`");
+    }
+
+    #[test]
+    fn validate_checks_function_arguments() {
+        let sort: Sort = "div(price, max(weight, 1.5)) desc, if(exists(stock),termfreq(tags,'a,b'),-1) asc".parse().unwrap();
+        assert!(sort.validate(&["price", "weight", "stock", "tags"]).is_ok());
+        assert_eq!(function_fields("sum(x, \"y\", 2, true, $p)"), vec!["x", "$p"]);
+        let error = sort.validate(&["price", "stock", "tags"]).expect_err("weight is not allowed");
+        assert_eq!(error.to_string(), "Invalid request parameter: `sorting by weight is not allowed`");
+        assert!("geodist() asc".parse::<Sort>().unwrap().validate(&[]).is_ok());
+        assert!("geodist($sfield,$pt) asc".parse::<Sort>().unwrap().validate(&["$sfield"]).is_err());
+    }
+}
@@ -0,0 +1,335 @@
+//! Update command batches, mixing adds, deletes, commit, optimize and rollback in one request.
+//!
+//! Solr executes the commands in order. The body repeats keys, so it is sent as raw JSON.
+//!
+//! ```rust
+//! use serde_json::json;
+//! use rsolr::Client;
+//! use rsolr::update::{AddOptions, CommitOptions, UpdateBatch};
+//!
+//! let batch = UpdateBatch::new()
+//!     .add(json!({"id": "1", "title": "Rust"}))
+//!     .add_with(json!({"id": "2"}), AddOptions::new().overwrite(false).commit_within(5000))
+//!     .delete_ids(["3", "4"])
+//!     .delete_query("type:draft")
+//!     .commit(CommitOptions::new().soft_commit(true).wait_searcher(false));
+//! assert_eq!(batch.to_string(), r#"{"add":{"doc":{"id":"1","title":"Rust"}},"add":{"commitWithin":5000,"doc":{"id":"2"},"overwrite":false},"delete":["3","4"],"delete":{"query":"type:draft"},"commit":{"softCommit":true,"waitSearcher":false}}"#);
+//!
+//! fn send(batch: &UpdateBatch) {
+//!     let mut client = Client::new("http://solr:8983", "collection");
+//!     client.update_batch(batch).versions().run().expect("request failed.");
+//!     let response = client.get_update_response().expect("parsing failed");
+//!     for added in &response.adds {
+//!         println!("{} added in version {}", added.id, added.version);
+//!     }
+//!     for error in response.errors() {
+//!         println!("{:?} of {} failed: {}", error.error_type, error.id, error.message);
+//!     }
+//! }
+//! ```
+//!
+//! Commit options apply to any update request as well, instead of hard committing each of them.
+//!
+//! ```rust
+//! use serde_json::json;
+//! use rsolr::Client;
+//! use rsolr::update::CommitOptions;
+//!
+//! fn upload() {
+//!     Client::new("http://solr:8983", "collection")
+//!         .upload_json(json!({"id": "1"}))
+//!         .commit_within(10000)
+//!         .run().expect("request failed.");
+//!     Client::new("http://solr:8983", "collection")
+//!         .upload_json(json!({"id": "2"}))
+//!         .commit_options(&CommitOptions::new().soft_commit(true))
+//!         .run().expect("request failed.");
+//! }
+//! ```
+//!
+//! Large id lists can be deleted in chunks, one request each, sent right away.
+//!
+//! ```rust
+//! use rsolr::Client;
+//! use rsolr::update::DeleteById;
+//!
+//! fn delete(ids: &[String]) {
+//!     Client::new("http://solr:8983", "collection")
+//!         .delete_chunk_size(500)
+//!         .delete_chunked(ids.iter().map(|id| DeleteById::new(id).route("tenant1!")))
+//!         .expect("request failed.");
+//! }
+//! ```
+
+use std::fmt::{Display, Formatter};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+/// Options of an add command.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AddOptions {
+    overwrite: Option<bool>,
+    commit_within: Option<u32>
+}
+
+impl AddOptions {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// False skips the uniqueKey check, documents with the same id are duplicated.
+    pub fn overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = Some(overwrite);
+        self
+    }
+
+    /// Milliseconds within the document is committed.
+    pub fn commit_within(mut self, milliseconds: u32) -> Self {
+        self.commit_within = Some(milliseconds);
+        self
+    }
+}
+
+/// Options of a commit, unset ones are left to Solr.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommitOptions {
+    soft_commit: Option<bool>,
+    wait_searcher: Option<bool>,
+    open_searcher: Option<bool>,
+    expunge_deletes: Option<bool>
+}
+
+impl CommitOptions {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the changes visible without flushing them to stable storage.
+    pub fn soft_commit(mut self, soft_commit: bool) -> Self {
+        self.soft_commit = Some(soft_commit);
+        self
+    }
+
+    /// Blocks until a new searcher is opened.
+    pub fn wait_searcher(mut self, wait_searcher: bool) -> Self {
+        self.wait_searcher = Some(wait_searcher);
+        self
+    }
+
+    /// False flushes the changes without making them visible, for hard commits.
+    pub fn open_searcher(mut self, open_searcher: bool) -> Self {
+        self.open_searcher = Some(open_searcher);
+        self
+    }
+
+    /// Merges away segments with deleted documents.
+    pub fn expunge_deletes(mut self, expunge_deletes: bool) -> Self {
+        self.expunge_deletes = Some(expunge_deletes);
+        self
+    }
+
+    /// The options by their Solr names.
+    pub fn params(&self) -> Vec<(&'static str, bool)> {
+        [
+            ("softCommit", self.soft_commit),
+            ("waitSearcher", self.wait_searcher),
+            ("openSearcher", self.open_searcher),
+            ("expungeDeletes", self.expunge_deletes)
+        ]
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|value| (key, value)))
+            .collect()
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Object(self.params().into_iter().map(|(key, value)| (key.to_owned(), Value::Bool(value))).collect())
+    }
+}
+
+/// Document to delete by its uniqueKey, with the shard route or expected version if needed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeleteById {
+    id: String,
+    route: Option<String>,
+    version: Option<i64>
+}
+
+impl DeleteById {
+
+    pub fn new(id: &str) -> Self {
+        DeleteById { id: id.to_owned(), route: None, version: None }
+    }
+
+    /// The `_route_`, the shard of the document in collections with implicit router or custom compositeId routing.
+    pub fn route(mut self, route: &str) -> Self {
+        self.route = Some(route.to_owned());
+        self
+    }
+
+    /// The `_version_`, deletes only if it matches, see atomic_update::AtomicUpdate::version.
+    pub fn version(mut self, version: i64) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Plain id, or an object if routed or versioned.
+    fn to_value(&self) -> Value {
+        if self.route.is_none() && self.version.is_none() {
+            return json!(self.id);
+        }
+        let mut delete = Map::new();
+        delete.insert("id".to_owned(), json!(self.id));
+        if let Some(route) = &self.route {
+            delete.insert("_route_".to_owned(), json!(route));
+        }
+        if let Some(version) = self.version {
+            delete.insert("_version_".to_owned(), json!(version));
+        }
+        Value::Object(delete)
+    }
+}
+
+impl From<&str> for DeleteById {
+    fn from(id: &str) -> Self {
+        DeleteById::new(id)
+    }
+}
+
+impl From<String> for DeleteById {
+    fn from(id: String) -> Self {
+        DeleteById { id, route: None, version: None }
+    }
+}
+
+impl From<&String> for DeleteById {
+    fn from(id: &String) -> Self {
+        DeleteById::new(id)
+    }
+}
+
+/// Update commands, rendered as the JSON body of the `update` handler.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UpdateBatch {
+    commands: Vec<(&'static str, Value)>
+}
+
+impl UpdateBatch {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn add<D: Serialize>(self, document: D) -> Self {
+        self.add_with(document, AddOptions::default())
+    }
+
+    pub fn add_with<D: Serialize>(self, document: D, options: AddOptions) -> Self {
+        let mut add = Map::new();
+        add.insert("doc".to_owned(), serde_json::to_value(document).unwrap());
+        if let Some(overwrite) = options.overwrite {
+            add.insert("overwrite".to_owned(), json!(overwrite));
+        }
+        if let Some(commit_within) = options.commit_within {
+            add.insert("commitWithin".to_owned(), json!(commit_within));
+        }
+        self.command("add", Value::Object(add))
+    }
+
+    /// Deletes the documents by their uniqueKey, accepts ids or DeleteById entries.
+    pub fn delete_ids<I: IntoIterator<Item = D>, D: Into<DeleteById>>(self, ids: I) -> Self {
+        let ids: Vec<Value> = ids.into_iter().map(|id| id.into().to_value()).collect();
+        self.command("delete", Value::Array(ids))
+    }
+
+    /// Deletes the documents matching the query, accepts a raw string or a query::Query.
+    pub fn delete_query<Q: Display>(self, query: Q) -> Self {
+        self.command("delete", json!({ "query": query.to_string() }))
+    }
+
+    pub fn commit(self, options: CommitOptions) -> Self {
+        self.command("commit", options.to_value())
+    }
+
+    /// Merges the index into one segment.
+    pub fn optimize(self) -> Self {
+        self.command("optimize", json!({}))
+    }
+
+    /// Merges the index down to the number of segments.
+    pub fn optimize_to(self, max_segments: u32) -> Self {
+        self.command("optimize", json!({ "maxSegments": max_segments }))
+    }
+
+    /// Drops the uncommitted changes, not available in SolrCloud.
+    pub fn rollback(self) -> Self {
+        self.command("rollback", json!({}))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    fn command(mut self, name: &'static str, value: Value) -> Self {
+        self.commands.push((name, value));
+        self
+    }
+}
+
+impl Display for UpdateBatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{")?;
+        for (index, (name, value)) in self.commands.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "\"{}\":{}", name, value)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_commands_in_order_with_repeated_keys() {
+        let batch = UpdateBatch::new()
+            .delete_query("*:*")
+            .add(json!({"id": 1}))
+            .add(json!({"id": 2}))
+            .optimize_to(2)
+            .optimize()
+            .rollback();
+        assert_eq!(batch.to_string(), r#"{"delete":{"query":"*:*"},"add":{"doc":{"id":1}},"add":{"doc":{"id":2}},"optimize":{"maxSegments":2},"optimize":{},"rollback":{}}"#);
+    }
+
+    #[test]
+    fn commit_renders_set_options_only() {
+        assert_eq!(UpdateBatch::new().commit(CommitOptions::new()).to_string(), r#"{"commit":{}}"#);
+        let options = CommitOptions::new().expunge_deletes(true).open_searcher(false).soft_commit(false);
+        assert_eq!(options.params(), vec![("softCommit", false), ("openSearcher", false), ("expungeDeletes", true)]);
+        assert_eq!(UpdateBatch::new().commit(options).to_string(), r#"{"commit":{"expungeDeletes":true,"openSearcher":false,"softCommit":false}}"#);
+    }
+
+    #[test]
+    fn delete_ids_with_route_and_version() {
+        let batch = UpdateBatch::new().delete_ids([
+            DeleteById::new("a"),
+            DeleteById::new("b").route("shard1"),
+            DeleteById::new("c").version(42).route("tenant1!")
+        ]);
+        assert_eq!(batch.to_string(), r#"{"delete":["a",{"_route_":"shard1","id":"b"},{"_route_":"tenant1!","_version_":42,"id":"c"}]}"#);
+        let ids = vec!["x".to_owned()];
+        assert_eq!(UpdateBatch::new().delete_ids(&ids).to_string(), r#"{"delete":["x"]}"#);
+    }
+
+    #[test]
+    fn empty_batch() {
+        assert!(UpdateBatch::new().is_empty());
+        assert_eq!(UpdateBatch::new().to_string(), "{}");
+    }
+}